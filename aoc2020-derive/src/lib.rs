@@ -0,0 +1,417 @@
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+const PRIMITIVES: [&str; 17] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32",
+    "f64", "bool", "char", "String",
+];
+
+/// Registers a function as one part of a day's solution.
+///
+/// ```ignore
+/// #[aoc(day = 1, part = 1)]
+/// fn part1(input: &[u64]) -> u64 { ... }
+///
+/// #[aoc(day = 1, part = 2)]
+/// fn part2(input: Vec<u64>) -> u64 { ... }
+/// ```
+///
+/// Both parts of a day have to live in the same module and generate the same
+/// `Solver` as `register!`. The input shape is taken from the parameter type:
+///
+/// - `&str` is the verbatim input
+/// - `&[Vec<T>]` or `Vec<Vec<T>>` are blocks of lines, each line converted with `From<String>`
+/// - `&[T]` or `Vec<T>` are lines, parsed with `FromStr` for primitives and
+///   converted with `From<String>` otherwise. Use `input = parse` to parse other types.
+///
+/// Part 1 may take its input by reference, mutable reference or by value,
+/// the latter requires the input to be `Clone`. Part 2 always runs last and
+/// can take ownership.
+#[proc_macro_attribute]
+pub fn aoc(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let generated = parse_args(attr)
+        .and_then(|args| Ok((args, parse_fn(item.clone())?)))
+        .map(|(args, fun)| generate(&args, &fun))
+        .unwrap_or_else(|e| e.into_compile_error());
+
+    let mut out = item;
+    out.extend(generated);
+    out
+}
+
+struct Error {
+    span: Span,
+    msg: String,
+}
+
+impl Error {
+    fn new(span: Span, msg: impl Into<String>) -> Self {
+        Self {
+            span,
+            msg: msg.into(),
+        }
+    }
+
+    fn into_compile_error(self) -> TokenStream {
+        let mut msg = Literal::string(&self.msg);
+        msg.set_span(self.span);
+        let mut body = Group::new(Delimiter::Brace, TokenTree::from(msg).into());
+        body.set_span(self.span);
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.span);
+        vec![
+            TokenTree::from(Ident::new("compile_error", self.span)),
+            bang.into(),
+            body.into(),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+struct Args {
+    day: u8,
+    part: u8,
+    parse: bool,
+}
+
+fn parse_args(attr: TokenStream) -> Result<Args> {
+    let mut day = None;
+    let mut part = None;
+    let mut parse = false;
+
+    let tokens = attr.into_iter().collect::<Vec<_>>();
+    for arg in tokens.split(|t| is_punct(t, ',')).filter(|a| !a.is_empty()) {
+        match arg {
+            [TokenTree::Ident(key), eq, value] if is_punct(eq, '=') => {
+                match (key.to_string().as_str(), value) {
+                    ("day", TokenTree::Literal(lit)) => {
+                        day = Some(parse_number(lit, 1..=25, "day")?);
+                    }
+                    ("part", TokenTree::Literal(lit)) => {
+                        part = Some(parse_number(lit, 1..=2, "part")?);
+                    }
+                    ("input", TokenTree::Ident(kind)) if kind.to_string() == "parse" => {
+                        parse = true;
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            key.span(),
+                            "expected `day = <1..=25>`, `part = <1|2>` or `input = parse`",
+                        ))
+                    }
+                }
+            }
+            other => {
+                return Err(Error::new(
+                    other[0].span(),
+                    "expected `day = <1..=25>`, `part = <1|2>` or `input = parse`",
+                ))
+            }
+        }
+    }
+
+    Ok(Args {
+        day: day.ok_or_else(|| Error::new(Span::call_site(), "missing `day = <1..=25>`"))?,
+        part: part.ok_or_else(|| Error::new(Span::call_site(), "missing `part = <1|2>`"))?,
+        parse,
+    })
+}
+
+fn parse_number(lit: &Literal, range: std::ops::RangeInclusive<u8>, what: &str) -> Result<u8> {
+    lit.to_string()
+        .parse::<u8>()
+        .ok()
+        .filter(|n| range.contains(n))
+        .ok_or_else(|| {
+            Error::new(
+                lit.span(),
+                format!(
+                    "{} must be between {} and {}",
+                    what,
+                    range.start(),
+                    range.end()
+                ),
+            )
+        })
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Pass {
+    Value,
+    Ref,
+    RefMut,
+}
+
+struct Fun {
+    name: Ident,
+    pass: Pass,
+    input: Vec<TokenTree>,
+    output: Vec<TokenTree>,
+    span: Span,
+}
+
+fn parse_fn(item: TokenStream) -> Result<Fun> {
+    let mut tokens = item.into_iter().skip_while(|t| !is_ident(t, "fn"));
+    let span = tokens
+        .next()
+        .ok_or_else(|| Error::new(Span::call_site(), "#[aoc] can only be used on functions"))?
+        .span();
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(name)) => name,
+        _ => return Err(Error::new(span, "expected a function name")),
+    };
+    let params = match tokens.next() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => g,
+        Some(t) => return Err(Error::new(t.span(), "#[aoc] functions cannot be generic")),
+        None => return Err(Error::new(name.span(), "expected parameters")),
+    };
+
+    let params = params.stream().into_iter().collect::<Vec<_>>();
+    let params = split_top_level(&params, ',');
+    let param = match &params[..] {
+        [param] => param,
+        _ => {
+            return Err(Error::new(
+                name.span(),
+                "#[aoc] functions take exactly one parameter, the puzzle input",
+            ))
+        }
+    };
+    let colon = param
+        .iter()
+        .enumerate()
+        .position(|(i, t)| {
+            is_punct(t, ':')
+                && !param.get(i + 1).is_some_and(|t| is_punct(t, ':'))
+                && !param[..i].last().is_some_and(|t| is_punct(t, ':'))
+        })
+        .ok_or_else(|| Error::new(name.span(), "expected a typed parameter"))?;
+    let (pass, input) = strip_reference(&param[colon + 1..]);
+
+    let output = match (tokens.next(), tokens.next()) {
+        (Some(dash), Some(gt)) if is_punct(&dash, '-') && is_punct(&gt, '>') => tokens
+            .take_while(|t| !is_ident(t, "where") && !is_group(t, Delimiter::Brace))
+            .collect::<Vec<_>>(),
+        _ => {
+            return Err(Error::new(
+                name.span(),
+                "#[aoc] functions have to return the answer",
+            ))
+        }
+    };
+
+    Ok(Fun {
+        name,
+        pass,
+        input: input.to_vec(),
+        output,
+        span,
+    })
+}
+
+fn strip_reference(ty: &[TokenTree]) -> (Pass, &[TokenTree]) {
+    match ty {
+        [amp, rest @ ..] if is_punct(amp, '&') => {
+            let rest = match rest {
+                [tick, TokenTree::Ident(_), rest @ ..] if is_punct(tick, '\'') => rest,
+                rest => rest,
+            };
+            match rest {
+                [m, rest @ ..] if is_ident(m, "mut") => (Pass::RefMut, rest),
+                rest => (Pass::Ref, rest),
+            }
+        }
+        ty => (Pass::Value, ty),
+    }
+}
+
+// Returns the `PuzzleInput` that produces the given parameter type.
+fn input_shape(fun: &Fun, parse: bool) -> Result<String> {
+    let element = match &fun.input[..] {
+        [TokenTree::Ident(s)] if s.to_string() == "str" && fun.pass == Pass::Ref => {
+            return Ok(String::from("::aoc2020::Raw"));
+        }
+        [TokenTree::Group(g)] if g.delimiter() == Delimiter::Bracket && fun.pass != Pass::Value => {
+            g.stream().into_iter().collect::<Vec<_>>()
+        }
+        [vec, lt, element @ .., gt]
+            if is_ident(vec, "Vec") && is_punct(lt, '<') && is_punct(gt, '>') =>
+        {
+            element.to_vec()
+        }
+        _ => {
+            let span = fun.input.first().map_or(fun.name.span(), TokenTree::span);
+            return Err(Error::new(
+                span,
+                "expected the input as `&str`, `&[T]`, `Vec<T>`, `&[Vec<T>]` or `Vec<Vec<T>>`",
+            ));
+        }
+    };
+
+    let shape = match &element[..] {
+        [vec, lt, line @ .., gt]
+            if is_ident(vec, "Vec") && is_punct(lt, '<') && is_punct(gt, '>') =>
+        {
+            format!("::aoc2020::Blocks<::aoc2020::As<{}>>", tokens(line))
+        }
+        [TokenTree::Ident(ty)] if parse || PRIMITIVES.contains(&ty.to_string().as_str()) => {
+            format!("::aoc2020::Parsing<{}>", ty)
+        }
+        element if parse => format!("::aoc2020::Parsing<{}>", tokens(element)),
+        element => format!("::aoc2020::As<{}>", tokens(element)),
+    };
+    Ok(shape)
+}
+
+fn generate(args: &Args, fun: &Fun) -> TokenStream {
+    let shape = match input_shape(fun, args.parse) {
+        Ok(shape) => shape,
+        Err(e) => return e.into_compile_error(),
+    };
+    let name = &fun.name;
+    let output = tokens(&fun.output);
+    let out = format!("<{} as ::aoc2020::PuzzleInput>::Out", shape);
+
+    let code = if args.part == 1 {
+        let arg = match fun.pass {
+            Pass::Value => "::std::clone::Clone::clone(&*input)",
+            Pass::Ref | Pass::RefMut => "input",
+        };
+        let file = format!("input/day{}.txt", args.day);
+        format!(
+            r#"
+            #[doc(hidden)]
+            fn __aoc_part1(input: &mut {out}) -> {output} {{
+                {name}({arg})
+            }}
+
+            const _: () = ::std::assert!(
+                __AOC_DAY_PART2 == {day},
+                "part 1 and part 2 are registered for different days"
+            );
+
+            pub(crate) struct Solver;
+
+            impl ::aoc2020::Solution for Solver {{
+                type Input = {shape};
+                type Output = {output};
+
+                #[inline]
+                fn puzzle_input() -> &'static str {{
+                    #[cfg(not(feature = "encrypted-inputs"))]
+                    {{
+                        ::std::include_str!("{file}")
+                    }}
+                    #[cfg(feature = "encrypted-inputs")]
+                    {{
+                        ::std::include_str!(::std::concat!(::std::env!("OUT_DIR"), "/", "{file}"))
+                    }}
+                }}
+
+                #[inline]
+                fn input_file() -> &'static str {{
+                    "{file}"
+                }}
+
+                #[inline]
+                fn run(mut input: {out}) -> ({output}, {output}) {{
+                    let res1 = __aoc_part1(&mut input);
+                    (res1, __aoc_part2(input))
+                }}
+            }}
+            "#,
+            out = out,
+            output = output,
+            name = name,
+            arg = arg,
+            day = args.day,
+            shape = shape,
+            file = file,
+        )
+    } else {
+        let arg = match fun.pass {
+            Pass::Value => "input",
+            Pass::Ref => "&input",
+            Pass::RefMut => "&mut input",
+        };
+        format!(
+            r#"
+            #[doc(hidden)]
+            const __AOC_DAY_PART2: u8 = {day};
+
+            #[doc(hidden)]
+            #[allow(unused_mut)]
+            fn __aoc_part2(mut input: {out}) -> {output} {{
+                {name}({arg})
+            }}
+            "#,
+            day = args.day,
+            out = out,
+            output = output,
+            name = name,
+            arg = arg,
+        )
+    };
+
+    let mut generated = code.parse::<TokenStream>().unwrap();
+    generated = respan(generated, fun.span);
+    generated
+}
+
+// Points errors in generated code, e.g. mismatched answer types, at the function.
+fn respan(stream: TokenStream, span: Span) -> TokenStream {
+    stream
+        .into_iter()
+        .map(|mut t| {
+            if let TokenTree::Group(g) = &t {
+                let mut group = Group::new(g.delimiter(), respan(g.stream(), span));
+                group.set_span(span);
+                t = group.into();
+            } else {
+                t.set_span(span.resolved_at(t.span()));
+            }
+            t
+        })
+        .collect()
+}
+
+fn split_top_level(tokens: &[TokenTree], sep: char) -> Vec<&[TokenTree]> {
+    let mut parts = Vec::new();
+    let mut depth = 0_i32;
+    let mut start = 0;
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+            TokenTree::Punct(p) if p.as_char() == '>' => depth -= 1,
+            TokenTree::Punct(p) if p.as_char() == sep && depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < tokens.len() {
+        parts.push(&tokens[start..]);
+    }
+    parts
+}
+
+fn tokens(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
+
+fn is_punct(t: &TokenTree, c: char) -> bool {
+    matches!(t, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+fn is_ident(t: &TokenTree, name: &str) -> bool {
+    matches!(t, TokenTree::Ident(i) if i.to_string() == name)
+}
+
+fn is_group(t: &TokenTree, delimiter: Delimiter) -> bool {
+    matches!(t, TokenTree::Group(g) if g.delimiter() == delimiter)
+}
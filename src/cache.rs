@@ -0,0 +1,102 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const DIR_ENV: &str = "AOC_CACHE_DIR";
+
+pub struct Cache {
+    dir: PathBuf,
+    build: u64,
+}
+
+impl Cache {
+    // The build hash covers the whole executable, so any rebuild of
+    // any solver invalidates all answers. Without it there is no way to
+    // tell stale answers apart, so there is no cache at all.
+    pub fn open() -> Option<Self> {
+        let exe = env::current_exe().and_then(fs::read).ok()?;
+        Some(Self::new(Self::default_dir(), hash(&exe)))
+    }
+
+    pub fn default_dir() -> PathBuf {
+        env::var_os(DIR_ENV).map_or_else(
+            || Path::new(env!("CARGO_MANIFEST_DIR")).join("target/aoc-cache"),
+            PathBuf::from,
+        )
+    }
+
+    pub fn new(dir: impl Into<PathBuf>, build: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            build,
+        }
+    }
+
+    pub fn get(&self, day: u8, part: u8, input: &str) -> Option<String> {
+        fs::read_to_string(self.entry(day, part, input)).ok()
+    }
+
+    // Caching is best effort, an answer that cannot be written is
+    // simply computed again next time.
+    pub fn put(&self, day: u8, part: u8, input: &str, answer: &str) {
+        if fs::create_dir_all(&self.dir).is_ok() {
+            let _ = fs::write(self.entry(day, part, input), answer);
+        }
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+
+    fn entry(&self, day: u8, part: u8, input: &str) -> PathBuf {
+        self.dir.join(format!(
+            "day{:02}-part{}-{:016x}-{:016x}",
+            day,
+            part,
+            hash(input.as_bytes()),
+            self.build
+        ))
+    }
+}
+
+// FNV-1a, which unlike the std hashers is stable across Rust versions
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, build: u64) -> Cache {
+        let dir = env::temp_dir().join(format!("aoc2020-cache-{}-{}", name, std::process::id()));
+        Cache::new(dir, build)
+    }
+
+    #[test]
+    fn fnv1a() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn entries_are_keyed_by_input_and_build() {
+        let cache = temp_cache("keys", 1);
+        cache.put(7, 1, "input", "222");
+        assert_eq!(cache.get(7, 1, "input").as_deref(), Some("222"));
+        assert_eq!(cache.get(7, 2, "input"), None);
+        assert_eq!(cache.get(8, 1, "input"), None);
+        assert_eq!(cache.get(7, 1, "other input"), None);
+        assert_eq!(Cache::new(&cache.dir, 2).get(7, 1, "input"), None);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get(7, 1, "input"), None);
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+
+// All numbers `x` with `x ≡ residue (mod modulus)`, the residue is
+// always in `0..modulus`. As the solution of a system, the modulus is
+// the period after which the solution repeats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Congruence {
+    pub residue: i128,
+    pub modulus: i128,
+}
+
+impl Congruence {
+    pub fn new(residue: i128, modulus: i128) -> Result<Self, CrtError> {
+        if modulus < 1 {
+            return Err(CrtError::InvalidModulus(modulus));
+        }
+        Ok(Self {
+            residue: residue.rem_euclid(modulus),
+            modulus,
+        })
+    }
+
+    // Combines both congruences into one that holds exactly when both do.
+    // The moduli do not have to be coprime.
+    pub fn merge(self, other: Self) -> Result<Self, CrtError> {
+        let (gcd, p, _) = extended_gcd(self.modulus, other.modulus);
+        let diff = other.residue - self.residue;
+        if diff % gcd != 0 {
+            return Err(CrtError::NoSolution {
+                first: self,
+                second: other,
+            });
+        }
+        // self.residue + self.modulus * k solves the second congruence for
+        // k ≡ diff / gcd * p (mod other.modulus / gcd).
+        let step = other.modulus / gcd;
+        let k = mul_mod((diff / gcd).rem_euclid(step), p.rem_euclid(step), step);
+        let modulus = self.modulus.checked_mul(step).ok_or(CrtError::Overflow)?;
+        Self::new(self.residue + self.modulus * k, modulus)
+    }
+
+    pub fn contains(&self, x: i128) -> bool {
+        x.rem_euclid(self.modulus) == self.residue
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CrtError {
+    InvalidModulus(i128),
+    // The two congruences never hold at the same time.
+    NoSolution {
+        first: Congruence,
+        second: Congruence,
+    },
+    // The period does not fit into an i128.
+    Overflow,
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtError::InvalidModulus(m) => write!(f, "modulus must be positive, not {}", m),
+            CrtError::NoSolution { first, second } => write!(
+                f,
+                "no number is {} mod {} and {} mod {}",
+                first.residue, first.modulus, second.residue, second.modulus
+            ),
+            CrtError::Overflow => f.write_str("the period does not fit into 128 bits"),
+        }
+    }
+}
+
+// Solves a system of `(residue, modulus)` constraints. An empty system
+// is solved by every number.
+pub fn solve(constraints: impl IntoIterator<Item = (i128, i128)>) -> Result<Congruence, CrtError> {
+    constraints
+        .into_iter()
+        .try_fold(Congruence::new(0, 1)?, |acc, (residue, modulus)| {
+            acc.merge(Congruence::new(residue, modulus)?)
+        })
+}
+
+// Returns `(gcd, p, q)` with `a * p + b * q == gcd`.
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut r0, mut r1) = (a, b);
+    let (mut p0, mut p1) = (1, 0);
+    let (mut q0, mut q1) = (0, 1);
+    while r1 != 0 {
+        let quot = r0 / r1;
+        (r0, r1) = (r1, r0 - quot * r1);
+        (p0, p1) = (p1, p0 - quot * p1);
+        (q0, q1) = (q1, q0 - quot * q1);
+    }
+    if r0 < 0 {
+        (-r0, -p0, -q0)
+    } else {
+        (r0, p0, q0)
+    }
+}
+
+// `a * b % m` for `a` and `b` in `0..m`, without overflowing.
+fn mul_mod(a: i128, b: i128, m: i128) -> i128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    let (mut a, mut b, mut product) = (a, b, 0);
+    while b > 0 {
+        if b & 1 == 1 {
+            product = add_mod(product, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    product
+}
+
+fn add_mod(a: i128, b: i128, m: i128) -> i128 {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coprime() {
+        let res = solve(vec![(0, 3), (3, 4), (4, 5)]).unwrap();
+        assert_eq!(res, Congruence::new(39, 60).unwrap());
+        assert_eq!(solve(None), Ok(Congruence::new(0, 1).unwrap()));
+        assert_eq!(solve(vec![(-1, 7)]), Ok(Congruence::new(6, 7).unwrap()));
+    }
+
+    #[test]
+    fn not_coprime() {
+        assert_eq!(
+            solve(vec![(2, 4), (4, 6)]),
+            Ok(Congruence::new(10, 12).unwrap())
+        );
+        assert_eq!(
+            solve(vec![(1, 4), (2, 6)]),
+            Err(CrtError::NoSolution {
+                first: Congruence::new(1, 4).unwrap(),
+                second: Congruence::new(2, 6).unwrap(),
+            })
+        );
+        assert_eq!(solve(vec![(1, 0)]), Err(CrtError::InvalidModulus(0)));
+    }
+
+    #[test]
+    fn against_brute_force() {
+        for m1 in 1..=12 {
+            for m2 in 1..=12 {
+                for a1 in 0..m1 {
+                    for a2 in 0..m2 {
+                        let expected = (0..m1 * m2).find(|x| x % m1 == a1 && x % m2 == a2);
+                        match solve(vec![(a1, m1), (a2, m2)]) {
+                            Ok(res) => {
+                                assert_eq!(Some(res.residue), expected);
+                                assert_eq!(res.modulus, m1 * m2 / extended_gcd(m1, m2).0);
+                                assert!(res.contains(res.residue + 5 * res.modulus));
+                            }
+                            Err(_) => assert_eq!(expected, None),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large() {
+        // The period is too large for an i64.
+        let p = (1 << 61) - 1;
+        let q = (1 << 61) - 31;
+        let res = solve(vec![(p - 1, p), (5, q)]).unwrap();
+        assert_eq!(res.modulus, p * q);
+        assert_eq!(res.residue % p, p - 1);
+        assert_eq!(res.residue % q, 5);
+
+        // The intermediate products are too large for an i128.
+        let m = (1 << 100) + 277;
+        let res = solve(vec![(12345, 1_000_003), (m - 2, m)]).unwrap();
+        assert_eq!(res.modulus, 1_000_003 * m / extended_gcd(1_000_003, m).0);
+        assert!(res.modulus > 1 << 110);
+        assert_eq!(res.residue % 1_000_003, 12345);
+        assert_eq!(res.residue % m, m - 2);
+
+        let r = (1 << 62) - 57;
+        let s = (1 << 62) - 87;
+        assert_eq!(
+            solve(vec![(0, p), (0, q), (0, r), (0, s)]),
+            Err(CrtError::Overflow)
+        );
+    }
+
+    #[test]
+    fn extended_euclid() {
+        for &(a, b) in &[(240, 46), (46, 240), (7, 0), (0, 7), (-12, 18)] {
+            let (gcd, p, q) = extended_gcd(a, b);
+            assert_eq!(a * p + b * q, gcd);
+            assert!(gcd >= 0);
+        }
+        assert_eq!(extended_gcd(240, 46).0, 2);
+    }
+}
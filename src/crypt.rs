@@ -0,0 +1,137 @@
+use std::convert::TryInto;
+
+pub const KEY_ENV: &str = "AOC_INPUT_KEY";
+
+const MAGIC: &[u8] = b"aoc2020\n";
+const NONCE_LEN: usize = 12;
+
+pub type Key = [u8; 32];
+pub type Nonce = [u8; NONCE_LEN];
+
+pub fn parse_key(hex: &str) -> Result<Key, String> {
+    let hex = hex.trim();
+    // Checked first, so that slicing below stays on char boundaries.
+    if !hex.is_ascii() || hex.len() != 64 {
+        return Err(format!("{} must be 64 hex digits", KEY_ENV));
+    }
+    let mut key = [0; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("{} must be 64 hex digits", KEY_ENV))?;
+    }
+    Ok(key)
+}
+
+pub fn encrypt(key: &Key, nonce: Nonce, plain: &[u8]) -> Vec<u8> {
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(plain);
+    chacha20(key, &nonce, 1, &mut data[NONCE_LEN..]);
+    data
+}
+
+pub fn decrypt(key: &Key, data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < NONCE_LEN + MAGIC.len() {
+        return Err(String::from("encrypted input is truncated"));
+    }
+    let (nonce, cipher) = data.split_at(NONCE_LEN);
+    let mut plain = cipher.to_vec();
+    chacha20(key, nonce.try_into().unwrap(), 1, &mut plain);
+    if !plain.starts_with(MAGIC) {
+        return Err(format!("could not decrypt input, is {} correct?", KEY_ENV));
+    }
+    Ok(plain.split_off(MAGIC.len()))
+}
+
+// ChaCha20 as specified in RFC 8439
+fn chacha20(key: &Key, nonce: &Nonce, counter: u32, data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let stream = block(key, nonce, counter.wrapping_add(i as u32));
+        chunk.iter_mut().zip(&stream).for_each(|(b, s)| *b ^= s);
+    }
+}
+
+fn block(key: &Key, nonce: &Nonce, counter: u32) -> [u8; 64] {
+    let mut state = [0_u32; 16];
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for (i, word) in key.chunks(4).enumerate() {
+        state[4 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+    state[12] = counter;
+    for (i, word) in nonce.chunks(4).enumerate() {
+        state[13 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+
+    let mut out = [0; 64];
+    for (i, (w, s)) in working.iter().zip(&state).enumerate() {
+        out[4 * i..4 * i + 4].copy_from_slice(&w.wrapping_add(*s).to_le_bytes());
+    }
+    out
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn rfc8439_encryption() {
+        let key = parse_key(KEY).unwrap();
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let mut data = *b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        chacha20(&key, &nonce, 1, &mut data);
+        assert_eq!(
+            &data[..16],
+            &[
+                0x6e, 0x2e, 0x35, 0x9a, 0x25, 0x68, 0xf9, 0x80, 0x41, 0xba, 0x07, 0x28, 0xdd, 0x0d,
+                0x69, 0x81
+            ]
+        );
+        assert_eq!(&data[data.len() - 4..], &[0x5e, 0x42, 0x87, 0x4d]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let key = parse_key(KEY).unwrap();
+        let data = encrypt(&key, [7; 12], b"1721\n979\n366\n");
+        assert_eq!(decrypt(&key, &data).unwrap(), b"1721\n979\n366\n");
+
+        let other = parse_key(&KEY.replace('0', "f")).unwrap();
+        assert!(decrypt(&other, &data).is_err());
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert!(parse_key(&KEY[2..]).is_err());
+        assert!(parse_key(&KEY.replace('a', "g")).is_err());
+        // 62 hex digits and one two byte character, 64 bytes in total.
+        let multibyte = format!("{}é", &KEY[2..]);
+        assert_eq!(multibyte.len(), 64);
+        assert!(parse_key(&multibyte).is_err());
+        assert!(parse_key(&format!("é{}", &KEY[2..])).is_err());
+    }
+}
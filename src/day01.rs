@@ -1,36 +1,222 @@
+use aoc2020::{Generated, Generator, Rng, Solution, StreamingSolution};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
+
 register!(
     "input/day1.txt";
     run(input: parse u64) -> u64 {
         input.sort_unstable();
         (
-            find_pair_and_prod(2020, &input).expect("no solution"),
-            find_triple_and_prod(2020, &input),
+            find_and_prod(2020, 2, &input).expect("no solution"),
+            find_and_prod(2020, 3, &input).expect("no solution"),
         )
     }
 );
 
-fn find_triple_and_prod(target_sum: u64, mut items: &[u64]) -> u64 {
-    while let Some((&item, rest)) = items.split_first() {
-        if let Some(remainder) = target_sum.checked_sub(item) {
-            if let Some(prod) = find_pair_and_prod(remainder, rest) {
-                return prod * item;
+impl StreamingSolution for Solver {
+    fn run_stream<I>(input: I) -> (u64, u64)
+    where
+        I: Iterator<Item = u64>,
+    {
+        Self::run(bounded_candidates(2020, 3, input))
+    }
+}
+
+// Only values up to the target can be part of a solution, and no value is
+// needed more than k times, so the retained input is bounded by the target.
+fn bounded_candidates(target_sum: u64, k: usize, input: impl Iterator<Item = u64>) -> Vec<u64> {
+    let mut counts = vec![0_u8; target_sum as usize + 1];
+    let max = u8::try_from(k).unwrap_or(u8::MAX);
+    for item in input.filter(|&item| item <= target_sum) {
+        let count = &mut counts[item as usize];
+        *count = (*count + 1).min(max);
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .flat_map(|(item, count)| std::iter::repeat_n(item as u64, usize::from(count)))
+        .collect()
+}
+
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 200;
+
+    // The pair uses one value below 600 and one above 1010, the triple uses
+    // three values in 600..=820. All other values are above 1010 and avoid
+    // every complement of the small values, so both solutions are unique.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        assert!(size >= 5, "need at least 5 entries");
+
+        let a = rng.range(1..=599);
+        let b = rng.range(600..=820);
+        let c = rng.range(600..=1420 - b);
+        let d = 2020 - b - c;
+        let small = [a, b, c, d];
+
+        let mut excluded = small.iter().map(|x| 2020 - x).collect::<HashSet<_>>();
+        for (i, x) in small.iter().enumerate() {
+            for y in &small[i + 1..] {
+                excluded.insert(2020 - x - y);
             }
-        };
-        items = rest;
+        }
+
+        let mut items = vec![a, 2020 - a, b, c, d];
+        while items.len() < size {
+            let item = rng.range(1011..=2019);
+            if !excluded.contains(&item) {
+                items.push(item);
+            }
+        }
+        rng.shuffle(&mut items);
+
+        Generated {
+            input: items.iter().map(|i| format!("{}\n", i)).collect(),
+            part1: Some((a * (2020 - a)).to_string()),
+            part2: Some((b * c * d).to_string()),
+        }
     }
-    panic!("no solution")
 }
 
-fn find_pair_and_prod(target_sum: u64, mut items: &[u64]) -> Option<u64> {
-    while let Some((&item, rest)) = items.split_first() {
-        if let Some(remainder) = target_sum.checked_sub(item) {
-            if let Ok(index) = rest.binary_search(&remainder) {
-                return Some(item * rest[index]);
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Strategy {
+    Hashing,
+    TwoPointer,
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hashing" => Ok(Strategy::Hashing),
+            "two-pointer" => Ok(Strategy::TwoPointer),
+            _ => Err(format!(
+                "unknown strategy {}, expected hashing or two-pointer",
+                s
+            )),
+        }
+    }
+}
+
+fn find_and_prod(target_sum: u64, k: usize, items: &[u64]) -> Option<u64> {
+    k_sums(target_sum, k, items, Strategy::TwoPointer)
+        .first()
+        .map(|solution| solution.iter().product())
+}
+
+// Finds all distinct combinations of k items that add up to the target sum,
+// each combination in ascending order. Pairs are found in linear time, so
+// k items take O(n^(k-1)).
+pub(crate) fn k_sums(
+    target_sum: u64,
+    k: usize,
+    items: &[u64],
+    strategy: Strategy,
+) -> Vec<Vec<u64>> {
+    let mut items = items
+        .iter()
+        .copied()
+        .filter(|&item| item <= target_sum)
+        .collect::<Vec<_>>();
+    items.sort_unstable();
+
+    let mut solutions = Vec::new();
+    let mut prefix = Vec::with_capacity(k);
+    find_k_sums(target_sum, k, &items, strategy, &mut prefix, &mut solutions);
+    solutions
+}
+
+fn find_k_sums(
+    target_sum: u64,
+    k: usize,
+    items: &[u64],
+    strategy: Strategy,
+    prefix: &mut Vec<u64>,
+    solutions: &mut Vec<Vec<u64>>,
+) {
+    let mut found = |pair: &[u64]| {
+        let mut solution = prefix.clone();
+        solution.extend_from_slice(pair);
+        solutions.push(solution);
+    };
+    match k {
+        0 if target_sum == 0 => found(&[]),
+        0 => {}
+        1 => {
+            if items.binary_search(&target_sum).is_ok() {
+                found(&[target_sum]);
+            }
+        }
+        2 => match strategy {
+            Strategy::Hashing => pair_sums_hashing(target_sum, items, found),
+            Strategy::TwoPointer => pair_sums_two_pointer(target_sum, items, found),
+        },
+        _ => {
+            for (i, &item) in items.iter().enumerate() {
+                if item.saturating_mul(k as u64) > target_sum {
+                    break;
+                }
+                if i > 0 && items[i - 1] == item {
+                    continue;
+                }
+                prefix.push(item);
+                find_k_sums(
+                    target_sum - item,
+                    k - 1,
+                    &items[i + 1..],
+                    strategy,
+                    prefix,
+                    solutions,
+                );
+                prefix.pop();
             }
+        }
+    }
+}
+
+fn pair_sums_hashing(target_sum: u64, items: &[u64], mut found: impl FnMut(&[u64])) {
+    let mut counts = HashMap::<u64, usize>::new();
+    for &item in items {
+        *counts.entry(item).or_default() += 1;
+    }
+    let mut distinct = counts.keys().copied().collect::<Vec<_>>();
+    distinct.sort_unstable();
+    for item in distinct {
+        let other = match target_sum.checked_sub(item) {
+            Some(other) => other,
+            None => break,
         };
-        items = rest;
+        if item < other && counts.contains_key(&other) || item == other && counts[&item] > 1 {
+            found(&[item, other]);
+        }
+    }
+}
+
+fn pair_sums_two_pointer(target_sum: u64, items: &[u64], mut found: impl FnMut(&[u64])) {
+    let (mut lo, mut hi) = (0, items.len());
+    while lo + 1 < hi {
+        let (a, b) = (items[lo], items[hi - 1]);
+        // a sum that does not fit is certainly too large
+        match a
+            .checked_add(b)
+            .map_or(Ordering::Greater, |sum| sum.cmp(&target_sum))
+        {
+            Ordering::Less => lo += 1,
+            Ordering::Greater => hi -= 1,
+            Ordering::Equal => {
+                found(&[a, b]);
+                while lo < hi && items[lo] == a {
+                    lo += 1;
+                }
+                while lo < hi && items[hi - 1] == b {
+                    hi -= 1;
+                }
+            }
+        }
     }
-    None
 }
 
 #[cfg(test)]
@@ -47,6 +233,50 @@ mod tests {
     }
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day1, ignore = "puzzle input not available")]
+    fn test_stream() {
+        let (res1, res2) = Solver::run_reader(Solver::puzzle_input().as_bytes());
+        assert_eq!(res1, 805731);
+        assert_eq!(res2, 192684960);
+    }
+
+    #[test]
+    fn test_k_sums() {
+        let items = [1, 2, 3, 4, 5, 5, 6, 10];
+        for &strategy in &[Strategy::Hashing, Strategy::TwoPointer] {
+            assert_eq!(
+                k_sums(10, 2, &items, strategy),
+                vec![vec![4, 6], vec![5, 5]]
+            );
+            assert_eq!(
+                k_sums(10, 3, &items, strategy),
+                vec![vec![1, 3, 6], vec![1, 4, 5], vec![2, 3, 5]]
+            );
+            assert_eq!(k_sums(10, 1, &items, strategy), vec![vec![10]]);
+            assert_eq!(k_sums(10, 4, &items, strategy), vec![vec![1, 2, 3, 4]]);
+            assert!(k_sums(100, 2, &items, strategy).is_empty());
+
+            let max = u64::MAX;
+            let large = [max - 10, 7, max - 7, max, 10, 3];
+            assert_eq!(
+                k_sums(max, 2, &large, strategy),
+                vec![vec![7, max - 7], vec![10, max - 10]]
+            );
+            assert_eq!(k_sums(max, 3, &large, strategy), vec![vec![3, 7, max - 10]]);
+            assert!(k_sums(max - 1, 2, &[max - 1, max - 1], strategy).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
+    #[test]
+    #[cfg_attr(no_puzzle_input_day1, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 805731);
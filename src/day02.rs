@@ -1,16 +1,11 @@
-use std::ops::RangeInclusive;
+use std::{fmt, ops::RangeInclusive, str::FromStr};
 
 register!(
     "input/day2.txt";
     run(input: PasswordInput) -> usize {
-        let (pt1, pt2): (Vec<_>, Vec<_>) =
-        input.into_iter().map(|l|
-            (is_valid_01(&l), is_valid_02(&l))
-        ).unzip();
-
         (
-            pt1.into_iter().filter(|x| *x).count(),
-            pt2.into_iter().filter(|x| *x).count()
+            count_valid(&input, &Rule::sled_rental()),
+            count_valid(&input, &Rule::toboggan()),
         )
     }
 );
@@ -33,27 +28,202 @@ impl From<String> for PasswordInput {
 
         PasswordInput {
             range: min..=max,
-            letter: letter.as_bytes()[0] as char,
+            letter: letter.chars().next().expect("Missing letter"),
             pass: pass.to_string(),
         }
     }
 }
 
-fn is_valid_01(input: &PasswordInput) -> bool {
-    let occurrences = input.pass.chars().filter(|c| *c == input.letter).count();
-    input.range.contains(&occurrences)
+impl fmt::Display for PasswordInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.range.start(),
+            self.range.end(),
+            self.letter,
+            self.pass
+        )
+    }
+}
+
+impl PasswordInput {
+    // positions are 1-based and count characters, not bytes
+    fn letter_at(&self, position: usize) -> bool {
+        position
+            .checked_sub(1)
+            .and_then(|pos| self.pass.chars().nth(pos))
+            == Some(self.letter)
+    }
+}
+
+pub(super) trait PasswordPolicy {
+    fn check(&self, input: &PasswordInput) -> Result<(), String>;
+
+    fn is_valid(&self, input: &PasswordInput) -> bool {
+        self.check(input).is_ok()
+    }
+}
+
+fn count_valid(input: &[PasswordInput], policy: &impl PasswordPolicy) -> usize {
+    input.iter().filter(|l| policy.is_valid(l)).count()
+}
+
+// Lists every invalid line together with the reason it failed.
+pub(super) fn report<'a>(
+    input: &'a [PasswordInput],
+    policy: &'a impl PasswordPolicy,
+) -> impl Iterator<Item = (usize, &'a PasswordInput, String)> + 'a {
+    input
+        .iter()
+        .enumerate()
+        .filter_map(move |(line, l)| policy.check(l).err().map(|e| (line + 1, l, e)))
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub(super) enum Rule {
+    Count,
+    First,
+    Second,
+    Not(Box<Rule>),
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    ExactlyOne(Vec<Rule>),
+}
+
+impl Rule {
+    pub(super) fn sled_rental() -> Self {
+        Rule::Count
+    }
+
+    pub(super) fn toboggan() -> Self {
+        Rule::ExactlyOne(vec![Rule::First, Rule::Second])
+    }
+}
+
+impl PasswordPolicy for Rule {
+    fn check(&self, input: &PasswordInput) -> Result<(), String> {
+        let letter = input.letter;
+        match self {
+            Rule::Count => {
+                let count = input.pass.chars().filter(|c| *c == letter).count();
+                if input.range.contains(&count) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "'{}' occurs {} times, expected {} to {}",
+                        letter,
+                        count,
+                        input.range.start(),
+                        input.range.end()
+                    ))
+                }
+            }
+            Rule::First | Rule::Second => {
+                let pos = if *self == Rule::First {
+                    *input.range.start()
+                } else {
+                    *input.range.end()
+                };
+                if input.letter_at(pos) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not at position {}", letter, pos))
+                }
+            }
+            Rule::Not(rule) => match rule.check(input) {
+                Ok(()) => Err(format!("expected not {}", rule)),
+                Err(_) => Ok(()),
+            },
+            Rule::All(rules) => rules.iter().try_for_each(|r| r.check(input)),
+            Rule::Any(rules) => {
+                if rules.iter().any(|r| r.is_valid(input)) {
+                    Ok(())
+                } else {
+                    Err(format!("none of {} holds", self))
+                }
+            }
+            Rule::ExactlyOne(rules) => match rules.iter().filter(|r| r.is_valid(input)).count() {
+                1 => Ok(()),
+                n => Err(format!("{} of {} hold, expected exactly one", n, self)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, rules) = match self {
+            Rule::Count => return f.write_str("count"),
+            Rule::First => return f.write_str("first"),
+            Rule::Second => return f.write_str("second"),
+            Rule::Not(rule) => return write!(f, "not({})", rule),
+            Rule::All(rules) => ("all", rules),
+            Rule::Any(rules) => ("any", rules),
+            Rule::ExactlyOne(rules) => ("one", rules),
+        };
+        write!(f, "{}(", name)?;
+        for (i, rule) in rules.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", rule)?;
+        }
+        f.write_str(")")
+    }
 }
 
-fn is_valid_02(input: &PasswordInput) -> bool {
-    let pos1 = *input.range.start() - 1;
-    let pos2 = *input.range.end() - 1;
-    let matches = input
-        .pass
-        .char_indices()
-        .filter(|(idx, _)| *idx == pos1 || *idx == pos2)
-        .filter(|(_, ch)| *ch == input.letter)
-        .count();
-    matches == 1
+// Parses policies like `one(first, second)` or `all(count, not(first))`,
+// as well as the names of the two built-in policies, `sled` and `toboggan`.
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, args) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (&s[..open], Some(&s[open + 1..s.len() - 1])),
+            Some(_) => return Err(format!("missing closing parenthesis in {}", s)),
+            None => (s, None),
+        };
+
+        let rules = || -> Result<Vec<Rule>, String> {
+            split_args(args.unwrap_or_default())
+                .into_iter()
+                .map(str::parse)
+                .collect()
+        };
+        match (name.trim(), args) {
+            ("sled", None) => Ok(Rule::sled_rental()),
+            ("toboggan", None) => Ok(Rule::toboggan()),
+            ("count", None) => Ok(Rule::Count),
+            ("first", None) => Ok(Rule::First),
+            ("second", None) => Ok(Rule::Second),
+            ("not", Some(arg)) => Ok(Rule::Not(Box::new(arg.parse()?))),
+            ("all", Some(_)) => Ok(Rule::All(rules()?)),
+            ("any", Some(_)) => Ok(Rule::Any(rules()?)),
+            ("one", Some(_)) => Ok(Rule::ExactlyOne(rules()?)),
+            (name, _) => Err(format!("unknown rule {}", name)),
+        }
+    }
+}
+
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
 }
 
 #[cfg(test)]
@@ -84,6 +254,71 @@ mod tests {
     }
 
     #[test]
+    fn test_ex() {
+        let input = Solver::parse_input(
+            "
+            1-3 a: abcde
+            1-3 b: cdefg
+            2-9 c: ccccccccc
+            ",
+        );
+        assert_eq!(count_valid(&input, &Rule::sled_rental()), 2);
+        assert_eq!(count_valid(&input, &Rule::toboggan()), 1);
+    }
+
+    #[test]
+    fn test_unicode_positions() {
+        let input = PasswordInput::from(String::from("2-3 ä: äöä"));
+        assert_eq!(input.letter, 'ä');
+        assert!(!Rule::First.is_valid(&input));
+        assert!(Rule::Second.is_valid(&input));
+        assert!(Rule::toboggan().is_valid(&input));
+        assert!(Rule::sled_rental().is_valid(&input));
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!("toboggan".parse(), Ok(Rule::toboggan()));
+        assert_eq!("one(first, second)".parse(), Ok(Rule::toboggan()));
+        let rule = "all(count, not(any(first, second)))"
+            .parse::<Rule>()
+            .unwrap();
+        assert_eq!(rule.to_string(), "all(count, not(any(first, second)))");
+        assert!("sometimes".parse::<Rule>().is_err());
+        assert!("all(count".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let input = Solver::parse_input(
+            "
+            1-3 a: abcde
+            1-3 b: cdefg
+            2-9 c: ccccccccc
+            ",
+        );
+        let sled = Rule::sled_rental();
+        let invalid = report(&input, &sled).collect::<Vec<_>>();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].0, 2);
+        assert_eq!(invalid[0].2, "'b' occurs 0 times, expected 1 to 3");
+
+        let toboggan = Rule::toboggan();
+        let invalid = report(&input, &toboggan).collect::<Vec<_>>();
+        assert_eq!(
+            invalid
+                .iter()
+                .map(|(line, _, reason)| (*line, reason.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, "0 of one(first, second) hold, expected exactly one"),
+                (3, "2 of one(first, second) hold, expected exactly one"),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg_attr(no_puzzle_input_day2, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 418);
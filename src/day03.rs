@@ -1,34 +1,144 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
 register!(
     "input/day3.txt";
     run(input: Vec<u8>) -> usize {
         (
-            count_trees((3, 1), &input),
+            count_trees(Slope::new(3, 1), &input),
             part2(input),
         )
     }
 );
 
-fn count_trees((slope_right, slope_down): (usize, usize), lines: &[Vec<u8>]) -> usize {
-    lines
-        .iter()
-        .step_by(slope_down)
-        .zip((0_usize..).step_by(slope_right))
-        .map(is_tree)
+pub(crate) const PART2_SLOPES: [Slope; 5] = [
+    Slope::new(1, 1),
+    Slope::new(3, 1),
+    Slope::new(5, 1),
+    Slope::new(7, 1),
+    Slope::new(1, 2),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Slope {
+    right: isize,
+    down: usize,
+}
+
+impl Slope {
+    pub(crate) const fn new(right: isize, down: usize) -> Self {
+        Self { right, down }
+    }
+
+    // Yields `(row, column)` for each step, the column is not wrapped yet
+    // and can be negative for slopes that go left.
+    fn path(self, lines: &[Vec<u8>]) -> impl Iterator<Item = (usize, isize)> {
+        (0..lines.len())
+            .step_by(self.down)
+            .zip((0_isize..).map(move |step| step * self.right))
+    }
+}
+
+// Parses `right,down`, e.g. `3,1`.
+impl FromStr for Slope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (right, down) = s
+            .split_once(',')
+            .ok_or_else(|| format!("expected right,down but got {}", s))?;
+        let right = right
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid right step {}", right))?;
+        let down = down
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid down step {}", down))?;
+        if down == 0 {
+            return Err(String::from("down step must be at least 1"));
+        }
+        Ok(Self { right, down })
+    }
+}
+
+impl fmt::Display for Slope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "right {}, down {}", self.right, self.down)
+    }
+}
+
+// Reads whitespace separated slopes, lines starting with `#` are ignored.
+pub(crate) fn parse_slopes(input: &str) -> Result<Vec<Slope>, String> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .flat_map(str::split_whitespace)
+        .map(str::parse)
+        .collect()
+}
+
+pub(crate) fn count_trees(slope: Slope, lines: &[Vec<u8>]) -> usize {
+    slope
+        .path(lines)
+        .map(|(y, x)| is_tree((&lines[y], x)))
         .map(usize::from)
         .sum()
 }
 
-fn is_tree((line, index): (impl AsRef<[u8]>, usize)) -> bool {
+fn is_tree((line, index): (impl AsRef<[u8]>, isize)) -> bool {
     let line = line.as_ref();
-    line[index % line.len()] == b'#'
+    line[index.rem_euclid(line.len() as isize) as usize] == b'#'
+}
+
+pub(crate) fn trees_per_slope(slopes: &[Slope], lines: &[Vec<u8>]) -> Vec<(Slope, usize)> {
+    slopes
+        .iter()
+        .map(|&slope| (slope, count_trees(slope, lines)))
+        .collect()
 }
 
 fn part2(lines: Vec<Vec<u8>>) -> usize {
-    count_trees((1, 1), &lines)
-        * count_trees((3, 1), &lines)
-        * count_trees((5, 1), &lines)
-        * count_trees((7, 1), &lines)
-        * count_trees((1, 2), &lines)
+    trees_per_slope(&PART2_SLOPES, &lines)
+        .into_iter()
+        .map(|(_, trees)| trees)
+        .product()
+}
+
+// Searches all slopes that go at most `max.right` to either side and at
+// most `max.down` down for the one that hits the fewest trees.
+// Ties go to the slope that is checked first.
+pub(crate) fn fewest_trees(max: Slope, lines: &[Vec<u8>]) -> Option<(Slope, usize)> {
+    let right = isize::try_from(max.right.unsigned_abs()).unwrap_or(isize::MAX);
+    (1..=max.down)
+        .flat_map(|down| (-right..=right).map(move |right| Slope::new(right, down)))
+        .map(|slope| (slope, count_trees(slope, lines)))
+        .min_by_key(|(_, trees)| *trees)
+}
+
+// Draws the map, repeated as often as the path needs, with `O` for open
+// squares and `X` for trees on the path.
+pub(crate) fn render(slope: Slope, lines: &[Vec<u8>]) -> String {
+    let path = slope.path(lines).collect::<Vec<_>>();
+    let width = lines.iter().map(Vec::len).max().unwrap_or_default().max(1) as isize;
+    let reach = path.last().map_or(0, |&(_, x)| x);
+    let first_tile = reach.min(0).div_euclid(width);
+    let tiles = (reach.max(0).div_euclid(width) - first_tile + 1) as usize;
+
+    let mut out = String::new();
+    let mut path = path.into_iter().peekable();
+    for (y, line) in lines.iter().enumerate() {
+        let mut row = line.repeat(tiles);
+        if let Some((_, x)) = path.next_if(|&(py, _)| py == y) {
+            // shorter rows are not drawn as far as the path goes
+            if let Some(square) = row.get_mut((x - first_tile * width) as usize) {
+                *square = if *square == b'#' { b'X' } else { b'O' };
+            }
+        }
+        out.push_str(&String::from_utf8_lossy(&row));
+        out.push('\n');
+    }
+    out
 }
 
 #[cfg(test)]
@@ -56,7 +166,77 @@ mod tests {
         assert_eq!(res2, 336);
     }
 
+    const EX: &str = "
+        ..##.......
+        #...#...#..
+        .#....#..#.
+        ..#.#...#.#
+        .#...##..#.
+        ..#.##.....
+        .#.#.#....#
+        .#........#
+        #.##...#...
+        #...##....#
+        .#..#...#.#
+    ";
+
+    #[test]
+    fn test_slopes() {
+        let lines = Solver::parse_input(EX);
+        let counts = trees_per_slope(
+            &parse_slopes("1,1 3,1\n# skip\n5,1\n7,1 1,2").unwrap(),
+            &lines,
+        );
+        assert_eq!(
+            counts.iter().map(|(_, trees)| *trees).collect::<Vec<_>>(),
+            vec![2, 7, 3, 4, 2]
+        );
+        assert_eq!(count_trees(Slope::new(0, 1), &lines), 3);
+        assert!(parse_slopes("1,0").is_err());
+        assert!(parse_slopes("3").is_err());
+    }
+
+    #[test]
+    fn test_fewest_trees() {
+        let lines = Solver::parse_input(EX);
+        let (slope, trees) = fewest_trees(Slope::new(7, 2), &lines).unwrap();
+        assert_eq!(trees, 0);
+        assert_eq!(count_trees(slope, &lines), 0);
+        assert_eq!(
+            fewest_trees(Slope::new(3, 1), &lines),
+            Some((Slope::new(2, 1), 1))
+        );
+        assert_eq!(count_trees(Slope::new(-2, 1), &lines), 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let lines = Solver::parse_input(EX);
+        let map = render(Slope::new(3, 1), &lines);
+        let mut rows = map.lines();
+        assert_eq!(rows.next(), Some("O.##.........##.........##......."));
+        assert_eq!(rows.next(), Some("#..O#...#..#...#...#..#...#...#.."));
+        assert_eq!(rows.next(), Some(".#....X..#..#....#..#..#....#..#."));
+        assert_eq!(
+            map.lines().last(),
+            Some(".#..#...#.#.#..#...#.#.#..#...X.#")
+        );
+        assert_eq!(map.matches('X').count(), 7);
+
+        let map = render(Slope::new(-1, 2), &lines);
+        let mut rows = map.lines();
+        assert_eq!(rows.next(), Some("..##.......O.##......."));
+        assert_eq!(rows.nth(1), Some(".#....#..#O.#....#..#."));
+
+        let lines = Solver::parse_input("..#..\n#\n.#.#.\n");
+        assert_eq!(
+            render(Slope::new(3, 1), &lines),
+            "O.#....#..\n##\n.#.#..X.#.\n"
+        );
+    }
+
     #[test]
+    #[cfg_attr(no_puzzle_input_day3, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 284);
@@ -1,6 +1,8 @@
 use std::{
     collections::HashMap,
-    ops::{Deref, DerefMut},
+    fmt,
+    ops::{Deref, DerefMut, RangeInclusive},
+    str::FromStr,
 };
 
 register!(
@@ -11,7 +13,7 @@ register!(
 );
 
 #[derive(Default, Debug)]
-struct Passport(HashMap<String, String>);
+pub(crate) struct Passport(HashMap<String, String>);
 
 impl Deref for Passport {
     type Target = HashMap<String, String>;
@@ -27,69 +29,323 @@ impl DerefMut for Passport {
     }
 }
 
-impl Passport {
-    fn is_valid_pt1(&self) -> bool {
-        self.contains_key("byr")
-            && self.contains_key("iyr")
-            && self.contains_key("eyr")
-            && self.contains_key("hgt")
-            && self.contains_key("hcl")
-            && self.contains_key("ecl")
-            && self.contains_key("pid")
-    }
-
-    fn is_valid_pt2(&self) -> bool {
-        let byr = self
-            .get("byr")
-            .and_then(|c| c.parse::<u16>().ok())
-            .filter(|byr| (1920..=2002).contains(byr))
-            .is_some();
-
-        let iyr = self
-            .get("iyr")
-            .and_then(|c| c.parse::<u16>().ok())
-            .filter(|iyr| (2010..=2020).contains(iyr))
-            .is_some();
-
-        let eyr = self
-            .get("eyr")
-            .and_then(|c| c.parse::<u16>().ok())
-            .filter(|eyr| (2020..=2030).contains(eyr))
-            .is_some();
-
-        let hgt = self
-            .get("hgt")
-            .filter(|c| {
-                let (value, unit) = c.split_at(c.len() - 2);
-                match (value.parse::<u8>().ok(), unit) {
-                    (Some(59..=76), "in") => true,
-                    (Some(150..=193), "cm") => true,
-                    _ => false,
-                }
+// Schemas list one field per line as `name required|optional check`,
+// where checks can be combined with `|`. Lines starting with `#` are
+// comments, so patterns themselves may still contain `#`.
+pub(crate) const PRESENCE_SCHEMA: &str = "
+    byr required any
+    iyr required any
+    eyr required any
+    hgt required any
+    hcl required any
+    ecl required any
+    pid required any
+    cid optional any
+";
+
+pub(crate) const STRICT_SCHEMA: &str = "
+    byr required int 1920..=2002
+    iyr required int 2010..=2020
+    eyr required int 2020..=2030
+    hgt required int 150..=193 cm | int 59..=76 in
+    hcl required pattern #[0-9a-f]{6}
+    ecl required enum amb blu brn gry grn hzl oth
+    pid required pattern [0-9]{9}
+    cid optional any
+";
+
+#[derive(Debug)]
+pub(crate) struct Schema(Vec<FieldRule>);
+
+#[derive(Debug)]
+struct FieldRule {
+    name: String,
+    required: bool,
+    checks: Vec<Check>,
+}
+
+#[derive(Debug)]
+enum Check {
+    Any,
+    Int(RangeInclusive<u64>, String),
+    Pattern(Pattern),
+    Enum(Vec<String>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Failure {
+    field: String,
+    reason: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.reason)
+    }
+}
+
+impl Schema {
+    pub(crate) fn presence() -> Self {
+        PRESENCE_SCHEMA.parse().unwrap()
+    }
+
+    pub(crate) fn strict() -> Self {
+        STRICT_SCHEMA.parse().unwrap()
+    }
+
+    pub(crate) fn validate(&self, passport: &Passport) -> Result<(), Vec<Failure>> {
+        let failures = self
+            .0
+            .iter()
+            .filter_map(|rule| {
+                let reason = match passport.get(&rule.name) {
+                    None if rule.required => String::from("missing"),
+                    None => return None,
+                    Some(value) if rule.checks.iter().any(|c| c.matches(value)) => return None,
+                    Some(value) => format!("invalid value {:?}, expected {}", value, rule),
+                };
+                Some(Failure {
+                    field: rule.name.clone(),
+                    reason,
+                })
             })
-            .is_some();
+            .collect::<Vec<_>>();
 
-        let hcl = self
-            .get("hcl")
-            .filter(|c| c.len() == 7)
-            .filter(|c| &c[0..1] == "#" && c[1..].bytes().all(|b| b.is_ascii_hexdigit()))
-            .is_some();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
 
-        let ecl = self
-            .get("ecl")
-            .filter(|c| ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"].contains(&&***c))
-            .is_some();
+    fn is_valid(&self, passport: &Passport) -> bool {
+        self.validate(passport).is_ok()
+    }
+}
 
-        let pid = self
-            .get("pid")
-            .filter(|c| c.len() == 9 && c.bytes().all(|c| c.is_ascii_digit()))
-            .is_some();
+impl FromStr for Schema {
+    type Err = String;
 
-        byr && iyr && eyr && hgt && hcl && ecl && pid
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|line| {
+                let mut parts = line.splitn(3, char::is_whitespace);
+                let name = parts.next().unwrap_or_default();
+                let required = match parts.next() {
+                    Some("required") => true,
+                    Some("optional") => false,
+                    _ => return Err(format!("expected required or optional in {:?}", line)),
+                };
+                let checks = parts
+                    .next()
+                    .ok_or_else(|| format!("missing check in {:?}", line))?
+                    .split('|')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+                Ok(FieldRule {
+                    name: name.to_string(),
+                    required,
+                    checks,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Schema)
     }
 }
 
-fn run(input: Vec<Vec<String>>) -> (usize, usize) {
+impl fmt::Display for FieldRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, check) in self.checks.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+impl Check {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Check::Any => true,
+            Check::Int(range, unit) => {
+                let digits = value.bytes().take_while(u8::is_ascii_digit).count();
+                let (number, suffix) = value.split_at(digits);
+                suffix == unit && number.parse().is_ok_and(|n| range.contains(&n))
+            }
+            Check::Pattern(pattern) => pattern.matches(value),
+            Check::Enum(values) => values.iter().any(|v| v == value),
+        }
+    }
+}
+
+impl FromStr for Check {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let check = match parts.next() {
+            Some("any") => Check::Any,
+            Some("int") => {
+                let range = parts.next().ok_or("missing range for int")?;
+                let (min, max) = range
+                    .split_once("..=")
+                    .ok_or_else(|| format!("expected min..=max but got {}", range))?;
+                let min = min.parse().map_err(|_| format!("invalid min {}", min))?;
+                let max = max.parse().map_err(|_| format!("invalid max {}", max))?;
+                Check::Int(min..=max, parts.next().unwrap_or_default().to_string())
+            }
+            Some("pattern") => Check::Pattern(parts.next().ok_or("missing pattern")?.parse()?),
+            Some("enum") => Check::Enum(parts.by_ref().map(String::from).collect()),
+            Some(other) => return Err(format!("unknown check {}", other)),
+            None => return Err(String::from("empty check")),
+        };
+        match parts.next() {
+            Some(extra) => Err(format!("unexpected {} in {:?}", extra, s.trim())),
+            None => Ok(check),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Any => f.write_str("any"),
+            Check::Int(range, unit) if unit.is_empty() => {
+                write!(f, "int {}..={}", range.start(), range.end())
+            }
+            Check::Int(range, unit) => {
+                write!(f, "int {}..={} {}", range.start(), range.end(), unit)
+            }
+            Check::Pattern(pattern) => write!(f, "pattern {}", pattern.source),
+            Check::Enum(values) => write!(f, "enum {}", values.join(" ")),
+        }
+    }
+}
+
+// A small subset of regular expressions: literals, `.`, `\d`, classes like
+// `[0-9a-f]` and the quantifiers `?`, `*`, `+`, `{n}` and `{n,m}`.
+// Patterns always have to match the whole value.
+#[derive(Debug)]
+struct Pattern {
+    source: String,
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug)]
+struct Piece {
+    atom: Atom,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Debug)]
+enum Atom {
+    Any,
+    Char(char),
+    Class(Vec<RangeInclusive<char>>),
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class(ranges) => ranges.iter().any(|r| r.contains(&c)),
+        }
+    }
+}
+
+impl Pattern {
+    fn matches(&self, value: &str) -> bool {
+        fn matches(pieces: &[Piece], chars: &[char]) -> bool {
+            match pieces.split_first() {
+                None => chars.is_empty(),
+                Some((piece, rest)) => {
+                    let most = chars
+                        .iter()
+                        .take(piece.max)
+                        .take_while(|c| piece.atom.matches(**c))
+                        .count();
+                    (piece.min..=most).rev().any(|n| matches(rest, &chars[n..]))
+                }
+            }
+        }
+        matches(&self.pieces, &value.chars().collect::<Vec<_>>())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars().peekable();
+        let mut pieces = Vec::new();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '.' => Atom::Any,
+                '\\' => match chars.next() {
+                    Some('d') => Atom::Class(vec!['0'..='9']),
+                    Some(c) => Atom::Char(c),
+                    None => return Err(format!("trailing escape in {}", s)),
+                },
+                '[' => {
+                    let mut ranges = Vec::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(start) if chars.next_if_eq(&'-').is_some() => match chars.next() {
+                                Some(end) if end != ']' => ranges.push(start..=end),
+                                _ => return Err(format!("unterminated range in {}", s)),
+                            },
+                            Some(c) => ranges.push(c..=c),
+                            None => return Err(format!("unterminated class in {}", s)),
+                        }
+                    }
+                    Atom::Class(ranges)
+                }
+                '?' | '*' | '+' | '{' => return Err(format!("nothing to repeat in {}", s)),
+                c => Atom::Char(c),
+            };
+
+            let (min, max) = match chars.peek() {
+                Some('?') => (0, 1),
+                Some('*') => (0, usize::MAX),
+                Some('+') => (1, usize::MAX),
+                Some('{') => {
+                    let _ = chars.next();
+                    let count = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                    let bound = |n: &str| {
+                        n.trim()
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid repetition {{{}}} in {}", count, s))
+                    };
+                    let (min, max) = match count.split_once(',') {
+                        Some((min, max)) => (bound(min)?, bound(max)?),
+                        None => (bound(&count)?, bound(&count)?),
+                    };
+                    pieces.push(Piece { atom, min, max });
+                    continue;
+                }
+                _ => (1, 1),
+            };
+            if (min, max) != (1, 1) {
+                let _ = chars.next();
+            }
+            pieces.push(Piece { atom, min, max });
+        }
+
+        Ok(Pattern {
+            source: s.to_string(),
+            pieces,
+        })
+    }
+}
+
+pub(crate) fn parse_passports(input: Vec<Vec<String>>) -> Vec<Passport> {
     input
         .into_iter()
         .map(|block| {
@@ -104,7 +360,19 @@ fn run(input: Vec<Vec<String>>) -> (usize, usize) {
                 pp
             })
         })
-        .map(|p| ((p.is_valid_pt1() as usize), (p.is_valid_pt2() as usize)))
+        .collect()
+}
+
+fn run(input: Vec<Vec<String>>) -> (usize, usize) {
+    let (presence, strict) = (Schema::presence(), Schema::strict());
+    parse_passports(input)
+        .iter()
+        .map(|p| {
+            (
+                (presence.is_valid(p) as usize),
+                (strict.is_valid(p) as usize),
+            )
+        })
         .fold((0, 0), |(r1, r2), (p1, p2)| (r1 + p1, r2 + p2))
 }
 
@@ -172,6 +440,82 @@ mod tests {
     }
 
     #[test]
+    fn patterns() {
+        let hcl = "#[0-9a-f]{6}".parse::<Pattern>().unwrap();
+        assert!(hcl.matches("#623a2f"));
+        assert!(!hcl.matches("#623a2"));
+        assert!(!hcl.matches("#623a2f0"));
+        assert!(!hcl.matches("623a2f"));
+        let p = r"a\d+.?[xy-z]*b{1,2}".parse::<Pattern>().unwrap();
+        assert!(p.matches("a12zb"));
+        assert!(p.matches("a1!xyzbb"));
+        assert!(!p.matches("ab"));
+        assert!(!p.matches("a1bbbb"));
+        assert!("[0-9".parse::<Pattern>().is_err());
+        assert!("*a".parse::<Pattern>().is_err());
+        assert!("a{x}".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn schema_from_text() {
+        let schema = "
+            # the pid must be a number between one and four digits
+            pid required pattern \\d{1,4}
+            hgt optional int 1..=3 m | enum tall short
+        "
+        .parse::<Schema>()
+        .unwrap();
+        let passports = parse_passports(Solver::parse_input(
+            "pid:12 hgt:2m
+
+            pid:12345 hgt:5
+
+            hgt:short",
+        ));
+        assert!(schema.validate(&passports[0]).is_ok());
+        assert_eq!(
+            schema.validate(&passports[1]).unwrap_err(),
+            vec![
+                Failure {
+                    field: String::from("pid"),
+                    reason: String::from(r#"invalid value "12345", expected pattern \d{1,4}"#)
+                },
+                Failure {
+                    field: String::from("hgt"),
+                    reason: String::from(
+                        r#"invalid value "5", expected int 1..=3 m | enum tall short"#
+                    )
+                },
+            ]
+        );
+        assert_eq!(
+            schema.validate(&passports[2]).unwrap_err()[0].to_string(),
+            "pid: missing"
+        );
+
+        assert!("pid mandatory any".parse::<Schema>().is_err());
+        assert!("pid required".parse::<Schema>().is_err());
+        assert!("pid required int 1..3".parse::<Schema>().is_err());
+        assert!("pid required float".parse::<Schema>().is_err());
+    }
+
+    #[test]
+    fn short_values() {
+        let passports = parse_passports(Solver::parse_input(
+            "hgt:5 byr:1 iyr:2015 eyr:2025 hcl:#123abc ecl:brn pid:000000001",
+        ));
+        let failures = Schema::strict().validate(&passports[0]).unwrap_err();
+        assert_eq!(
+            failures
+                .iter()
+                .map(|f| f.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["byr", "hgt"]
+        );
+    }
+
+    #[test]
+    #[cfg_attr(no_puzzle_input_day4, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 230);
@@ -1,33 +1,38 @@
-// use aoc2020::register2;
-use std::ops::{Deref, DerefMut};
+use aoc2020::aoc;
+use std::{
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
 
-register!(
-    "input/day5.txt";
-    run(input: Seat) -> u16 {
-        (max_seat_id(input.iter()), find_seat(input))
-    }
-);
+#[aoc(day = 5, part = 1, input = parse)]
+fn part1(input: &[Seat]) -> u64 {
+    max_seat_id(input.iter())
+}
+
+#[aoc(day = 5, part = 2, input = parse)]
+fn part2(input: Vec<Seat>) -> u64 {
+    find_seat(input)
+}
 
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq)]
-pub struct Seat(u16);
-
-impl From<String> for Seat {
-    fn from(input: String) -> Self {
-        Seat(
-            input
-                .bytes()
-                .map(|c| match c {
-                    b'F' | b'L' => 0,
-                    b'B' | b'R' => 1,
-                    x => unreachable!("not F, B, L, or R: {}", x),
-                })
-                .fold(0, |sum, digit| sum << 1 | digit),
-        )
+pub struct Seat(u64);
+
+impl FromStr for Seat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Codec::AIRLINE.decode(s)
+    }
+}
+
+impl From<u64> for Seat {
+    fn from(id: u64) -> Self {
+        Seat(id)
     }
 }
 
 impl Deref for Seat {
-    type Target = u16;
+    type Target = u64;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -40,22 +45,188 @@ impl DerefMut for Seat {
     }
 }
 
-fn max_seat_id<'a>(input: impl Iterator<Item = &'a Seat>) -> u16 {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Axis {
+    lower: char,
+    upper: char,
+    bits: u32,
+}
+
+impl Axis {
+    fn new(alphabet: &str, bits: u32) -> Result<Self, String> {
+        match alphabet.chars().collect::<Vec<_>>()[..] {
+            [lower, upper] if lower != upper => Ok(Self { lower, upper, bits }),
+            _ => Err(format!(
+                "expected two distinct characters for the lower and upper half, got {:?}",
+                alphabet
+            )),
+        }
+    }
+
+    fn size(self) -> u64 {
+        1 << self.bits
+    }
+}
+
+// Boarding passes are binary numbers, the row in the first `rows.bits`
+// characters and the column in the remaining `cols.bits` characters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Codec {
+    rows: Axis,
+    cols: Axis,
+}
+
+impl Codec {
+    pub(crate) const AIRLINE: Codec = Codec {
+        rows: Axis {
+            lower: 'F',
+            upper: 'B',
+            bits: 7,
+        },
+        cols: Axis {
+            lower: 'L',
+            upper: 'R',
+            bits: 3,
+        },
+    };
+
+    // Occupancy is tracked for every possible seat, this keeps it at 16 MiB.
+    const MAX_BITS: u32 = 24;
+
+    pub(crate) fn new(
+        rows: &str,
+        row_bits: u32,
+        cols: &str,
+        col_bits: u32,
+    ) -> Result<Self, String> {
+        let bits = row_bits.saturating_add(col_bits);
+        if bits > Self::MAX_BITS {
+            return Err(format!(
+                "at most {} bits are supported, got {}",
+                Self::MAX_BITS,
+                bits
+            ));
+        }
+        Ok(Self {
+            rows: Axis::new(rows, row_bits)?,
+            cols: Axis::new(cols, col_bits)?,
+        })
+    }
+
+    pub(crate) fn rows(&self) -> u64 {
+        self.rows.size()
+    }
+
+    pub(crate) fn cols(&self) -> u64 {
+        self.cols.size()
+    }
+
+    pub(crate) fn capacity(&self) -> u64 {
+        self.rows() * self.cols()
+    }
+
+    pub(crate) fn row_col(&self, seat: Seat) -> (u64, u64) {
+        (seat.0 >> self.cols.bits, seat.0 & (self.cols() - 1))
+    }
+
+    pub(crate) fn decode(&self, pass: &str) -> Result<Seat, String> {
+        let len = (self.rows.bits + self.cols.bits) as usize;
+        if pass.chars().count() != len {
+            return Err(format!("expected {} characters in {:?}", len, pass));
+        }
+        pass.chars()
+            .enumerate()
+            .try_fold(0, |id, (pos, c)| {
+                let axis = if pos < self.rows.bits as usize {
+                    self.rows
+                } else {
+                    self.cols
+                };
+                match c {
+                    c if c == axis.lower => Ok(id << 1),
+                    c if c == axis.upper => Ok(id << 1 | 1),
+                    c => Err(format!(
+                        "expected {} or {} at position {} in {:?}, got {:?}",
+                        axis.lower,
+                        axis.upper,
+                        pos + 1,
+                        pass,
+                        c
+                    )),
+                }
+            })
+            .map(Seat)
+    }
+
+    pub(crate) fn encode(&self, seat: Seat) -> Result<String, String> {
+        if seat.0 >= self.capacity() {
+            return Err(format!(
+                "seat {} is outside of a plane with {} seats",
+                seat.0,
+                self.capacity()
+            ));
+        }
+        let (row, col) = self.row_col(seat);
+        let digits = |axis: Axis, value: u64| {
+            (0..axis.bits).rev().map(move |bit| match value >> bit & 1 {
+                0 => axis.lower,
+                _ => axis.upper,
+            })
+        };
+        Ok(digits(self.rows, row)
+            .chain(digits(self.cols, col))
+            .collect())
+    }
+
+    // One line per row, `#` for occupied and `.` for empty seats.
+    pub(crate) fn occupancy_map(&self, seats: &[Seat]) -> String {
+        let occupied = occupied(self, seats);
+        let width = (self.rows() - 1).to_string().len();
+        occupied
+            .chunks(self.cols() as usize)
+            .enumerate()
+            .map(|(row, seats)| {
+                let seats = seats
+                    .iter()
+                    .map(|&taken| if taken { '#' } else { '.' })
+                    .collect::<String>();
+                format!("{:>width$} {}\n", row, seats, width = width)
+            })
+            .collect()
+    }
+}
+
+fn occupied(codec: &Codec, seats: &[Seat]) -> Vec<bool> {
+    let mut occupied = vec![false; codec.capacity() as usize];
+    for seat in seats.iter().filter(|s| s.0 < codec.capacity()) {
+        occupied[seat.0 as usize] = true;
+    }
+    occupied
+}
+
+pub(crate) fn empty_seats(codec: &Codec, seats: &[Seat]) -> Vec<Seat> {
+    occupied(codec, seats)
+        .into_iter()
+        .zip(0..)
+        .filter(|(taken, _)| !taken)
+        .map(|(_, id)| Seat(id))
+        .collect()
+}
+
+fn max_seat_id<'a>(input: impl Iterator<Item = &'a Seat>) -> u64 {
     **input.max().unwrap()
 }
 
-fn find_seat(mut seats: Vec<Seat>) -> u16 {
-    seats.sort_unstable();
-    let (first, rest) = seats.split_first().unwrap();
-    rest.iter()
-        .scan(**first, |prev, &current| {
-            let seat = *prev;
-            let diff = *current - seat;
-            *prev = *current;
-            Some((seat, diff))
+// Our seat is the empty one that has occupied seats on both sides.
+fn find_seat(seats: Vec<Seat>) -> u64 {
+    let codec = Codec::AIRLINE;
+    let occupied = occupied(&codec, &seats);
+    empty_seats(&codec, &seats)
+        .into_iter()
+        .map(|seat| seat.0)
+        .find(|&id| {
+            id > 0 && occupied[id as usize - 1] && occupied.get(id as usize + 1) == Some(&true)
         })
-        .find(|(_, diff)| *diff > 1)
-        .map(|(seat, _)| seat + 1)
         .unwrap()
 }
 
@@ -66,13 +237,55 @@ mod tests {
 
     #[test]
     fn test_parse_seat() {
-        assert_eq!(357, Seat::from(String::from("FBFBBFFRLR")).0);
-        assert_eq!(567, Seat::from(String::from("BFFFBBFRRR")).0);
-        assert_eq!(119, Seat::from(String::from("FFFBBBFRRR")).0);
-        assert_eq!(820, Seat::from(String::from("BBFFBBFRLL")).0);
+        assert_eq!(357, "FBFBBFFRLR".parse::<Seat>().unwrap().0);
+        assert_eq!(567, "BFFFBBFRRR".parse::<Seat>().unwrap().0);
+        assert_eq!(119, "FFFBBBFRRR".parse::<Seat>().unwrap().0);
+        assert_eq!(820, "BBFFBBFRLL".parse::<Seat>().unwrap().0);
+        assert!("FBFBBFFRLX".parse::<Seat>().is_err());
+        assert!("FBFBBFFRL".parse::<Seat>().is_err());
+    }
+
+    #[test]
+    fn test_codec() {
+        let codec = Codec::AIRLINE;
+        assert_eq!(codec.row_col(Seat(357)), (44, 5));
+        assert_eq!(codec.encode(Seat(820)).unwrap(), "BBFFBBFRLL");
+        assert!(codec.encode(Seat(1024)).is_err());
+
+        let codec = Codec::new("01", 2, "ab", 2).unwrap();
+        assert_eq!(codec.capacity(), 16);
+        for id in 0..16 {
+            let pass = codec.encode(Seat(id)).unwrap();
+            assert_eq!(codec.decode(&pass), Ok(Seat(id)));
+        }
+        assert_eq!(codec.encode(Seat(6)).unwrap(), "01ba");
+        assert!(codec.decode("0ab1").is_err());
+        assert!(Codec::new("00", 2, "ab", 2).is_err());
+        assert!(Codec::new("FB", 30, "LR", 3).is_err());
+        assert!(Codec::new("FB", 20, "LR", 5).is_err());
+        assert!(Codec::new("FB", u32::MAX, "LR", 1).is_err());
+        assert_eq!(Codec::new("FB", 20, "LR", 4).unwrap().capacity(), 1 << 24);
+    }
+
+    #[test]
+    fn test_empty_seats() {
+        let codec = Codec::new("FB", 2, "LR", 2).unwrap();
+        let seats = ["FFLR", "FFRL", "FFRR", "FBLL", "FBRL", "BBRR"]
+            .iter()
+            .map(|p| codec.decode(p).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            empty_seats(&codec, &seats),
+            [0, 5, 7, 8, 9, 10, 11, 12, 13, 14].map(Seat)
+        );
+        assert_eq!(
+            codec.occupancy_map(&seats),
+            "0 .###\n1 #.#.\n2 ....\n3 ...#\n"
+        );
     }
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day5, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 848);
@@ -1,66 +1,131 @@
-use std::ops::{BitAndAssign, BitOrAssign};
+use std::{
+    collections::BTreeMap,
+    iter::FromIterator,
+    ops::{BitAnd, BitOr, BitXor},
+};
 
 register!(
     "input/day6.txt";
-    run(input: chunk Answers) -> u32 {
+    run(input: chunk Answers) -> usize {
         (
-            run(input.clone(), BitOrAssign::bitor_assign),
-            run(input, BitAndAssign::bitand_assign)
+            input.iter().map(|group| anyone(group).len()).sum(),
+            input.iter().map(|group| everyone(group).len()).sum(),
         )
     }
 );
 
-#[derive(Copy, Clone, Debug, Default)]
-pub struct Answers(u32, bool);
+// A sparse bitset over all chars, stored as 64 bit words keyed by
+// `char / 64`, so that any alphabet only pays for the blocks it uses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Answers(BTreeMap<u32, u64>);
 
 impl From<String> for Answers {
     fn from(input: String) -> Self {
-        Answers(
-            input
-                .bytes()
-                .map(|b| b - b'a')
-                .fold(0_u32, |answer, b| answer | (1 << b)),
-            !input.is_empty(),
-        )
+        input.chars().collect()
+    }
+}
+
+impl FromIterator<char> for Answers {
+    fn from_iter<T: IntoIterator<Item = char>>(iter: T) -> Self {
+        let mut answers = Answers::default();
+        for c in iter {
+            answers.insert(c);
+        }
+        answers
     }
 }
 
-impl BitOrAssign for Answers {
-    fn bitor_assign(&mut self, Self(rhs, _): Self) {
-        self.0 |= rhs
+impl Answers {
+    pub(crate) fn insert(&mut self, answer: char) {
+        let answer = u32::from(answer);
+        *self.0.entry(answer / 64).or_default() |= 1 << (answer % 64);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.values().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.iter().flat_map(|(&block, &word)| {
+            (0..64)
+                .filter(move |bit| word & 1 << bit != 0)
+                .filter_map(move |bit| char::from_u32(block * 64 + bit))
+        })
     }
 }
 
-impl BitAndAssign for Answers {
-    fn bitand_assign(&mut self, Self(rhs, _): Self) {
-        if self.1 {
-            self.0 &= rhs;
-        } else {
-            self.0 = rhs;
-            self.1 = true;
+impl BitOr for &Answers {
+    type Output = Answers;
+
+    fn bitor(self, rhs: Self) -> Answers {
+        let mut blocks = self.0.clone();
+        for (&block, &word) in &rhs.0 {
+            *blocks.entry(block).or_default() |= word;
         }
+        Answers(blocks)
     }
 }
 
-impl Answers {
-    fn count(&self) -> u32 {
-        self.0.count_ones()
+impl BitAnd for &Answers {
+    type Output = Answers;
+
+    fn bitand(self, rhs: Self) -> Answers {
+        let blocks = self
+            .0
+            .iter()
+            .filter_map(|(&block, &word)| Some((block, word & rhs.0.get(&block)?)))
+            .filter(|(_, word)| *word != 0)
+            .collect();
+        Answers(blocks)
+    }
+}
+
+impl BitXor for &Answers {
+    type Output = Answers;
+
+    fn bitxor(self, rhs: Self) -> Answers {
+        let mut blocks = self.0.clone();
+        for (&block, &word) in &rhs.0 {
+            *blocks.entry(block).or_default() ^= word;
+        }
+        blocks.retain(|_, word| *word != 0);
+        Answers(blocks)
     }
 }
 
-fn run(input: Vec<Vec<Answers>>, op: impl Fn(&mut Answers, Answers)) -> u32 {
-    input
+// Questions that anyone in the group answered
+pub(crate) fn anyone(group: &[Answers]) -> Answers {
+    group.iter().fold(Answers::default(), |acc, a| &acc | a)
+}
+
+// Questions that everyone in the group answered
+pub(crate) fn everyone(group: &[Answers]) -> Answers {
+    match group.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.clone(), |acc, a| &acc & a),
+        None => Answers::default(),
+    }
+}
+
+// Questions that an odd number of group members answered
+pub(crate) fn odd_ones(group: &[Answers]) -> Answers {
+    group.iter().fold(Answers::default(), |acc, a| &acc ^ a)
+}
+
+pub(crate) fn at_least(group: &[Answers], k: usize) -> Answers {
+    histogram(group)
         .into_iter()
-        .map(|block| {
-            block
-                .into_iter()
-                .fold(Answers::default(), |mut group, line| {
-                    op(&mut group, line);
-                    group
-                })
-                .count()
-        })
-        .sum()
+        .filter(|(_, count)| *count >= k)
+        .map(|(answer, _)| answer)
+        .collect()
+}
+
+// How many members of the group answered each question
+pub(crate) fn histogram(group: &[Answers]) -> BTreeMap<char, usize> {
+    let mut counts = BTreeMap::new();
+    for answer in group.iter().flat_map(Answers::iter) {
+        *counts.entry(answer).or_default() += 1;
+    }
+    counts
 }
 
 #[cfg(test)]
@@ -69,12 +134,43 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day6, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 7110);
         assert_eq!(res2, 3628);
     }
 
+    #[test]
+    fn test_set_ops() {
+        let a = Answers::from(String::from("abcé"));
+        let b = Answers::from(String::from("bcdü😀"));
+        assert_eq!(a.len(), 4);
+        assert_eq!((&a | &b).iter().collect::<String>(), "abcdéü😀");
+        assert_eq!((&a & &b).iter().collect::<String>(), "bc");
+        assert_eq!((&a ^ &b).iter().collect::<String>(), "adéü😀");
+        assert_eq!(&a ^ &a, Answers::default());
+        assert_eq!(Answers::from(String::from("~{|}")).len(), 4);
+    }
+
+    #[test]
+    fn test_group_ops() {
+        let group = ["abc", "abd", "aé", "é"]
+            .iter()
+            .map(|a| Answers::from(a.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(anyone(&group).iter().collect::<String>(), "abcdé");
+        assert_eq!(everyone(&group), Answers::default());
+        assert_eq!(everyone(&group[..2]).iter().collect::<String>(), "ab");
+        assert_eq!(odd_ones(&group).iter().collect::<String>(), "acd");
+        assert_eq!(at_least(&group, 2).iter().collect::<String>(), "abé");
+        assert_eq!(at_least(&group, 3).iter().collect::<String>(), "a");
+        assert_eq!(
+            histogram(&group).into_iter().collect::<Vec<_>>(),
+            vec![('a', 3), ('b', 2), ('c', 1), ('d', 1), ('é', 2)]
+        );
+    }
+
     #[test]
     fn test_ex1() {
         let input = "
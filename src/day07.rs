@@ -1,3 +1,4 @@
+use aoc2020::{Generated, Generator, Rng};
 use std::collections::HashMap;
 
 register!(
@@ -8,44 +9,277 @@ register!(
 );
 
 fn run(input: impl Iterator<Item = Rule>) -> (usize, usize) {
-    let input = input
-        .map(|Rule { outer, inner }| (outer, inner))
-        .collect::<HashMap<_, _>>();
+    let graph = BagGraph::new(input).unwrap_or_else(|e| panic!("{}", e));
+    (
+        graph.containers(GOLD).unwrap().len(),
+        graph.count_inside(GOLD).unwrap_or_else(|e| panic!("{}", e)),
+    )
+}
+
+const GOLD: &str = "shiny gold";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum GraphError {
+    UnknownBag(String),
+    Cycle(Vec<String>),
+    // The count of bags does not fit into a usize.
+    Overflow,
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::UnknownBag(bag) => write!(f, "unknown bag {}", bag),
+            GraphError::Cycle(bags) => write!(f, "bags contain each other: {}", bags.join(" -> ")),
+            GraphError::Overflow => f.write_str("too many bags to count"),
+        }
+    }
+}
 
-    (run1(&input), run2(input))
+// Bags are numbered in order of appearance, bags that are only ever
+// mentioned as contents are assumed to contain nothing.
+#[derive(Debug)]
+pub(crate) struct BagGraph {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    contents: Vec<Vec<(usize, usize)>>,
+    containers: Vec<Vec<usize>>,
 }
 
-fn run1(input: &HashMap<String, Vec<(usize, String)>>) -> usize {
-    let mut total = 0;
-    for key in input.keys() {
-        let mut keys = vec![key.as_str()];
-        while !keys.is_empty() {
-            let key = keys.pop().unwrap();
-            for (_, color) in &input[key] {
-                if color == "shiny gold" {
-                    total += 1;
-                    keys.clear();
-                    break;
+impl BagGraph {
+    pub(crate) fn new(rules: impl IntoIterator<Item = Rule>) -> Result<Self, GraphError> {
+        let mut graph = BagGraph {
+            names: Vec::new(),
+            index: HashMap::new(),
+            contents: Vec::new(),
+            containers: Vec::new(),
+        };
+        for Rule { outer, inner } in rules {
+            let outer = graph.add(outer);
+            for (amt, bag) in inner {
+                let bag = graph.add(bag);
+                graph.contents[outer].push((amt, bag));
+                graph.containers[bag].push(outer);
+            }
+        }
+        graph.check_acyclic()?;
+        Ok(graph)
+    }
+
+    fn add(&mut self, bag: String) -> usize {
+        if let Some(&id) = self.index.get(&bag) {
+            return id;
+        }
+        let id = self.names.len();
+        self.index.insert(bag.clone(), id);
+        self.names.push(bag);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        id
+    }
+
+    fn id(&self, bag: &str) -> Result<usize, GraphError> {
+        self.index
+            .get(bag)
+            .copied()
+            .ok_or_else(|| GraphError::UnknownBag(bag.to_string()))
+    }
+
+    // Fails on any bag that (indirectly) contains itself. The depth-first
+    // walk keeps its own stack, as chains of bags can be arbitrarily long.
+    fn check_acyclic(&self) -> Result<(), GraphError> {
+        let mut done = vec![false; self.names.len()];
+        let mut on_stack = vec![false; self.names.len()];
+        for root in 0..self.names.len() {
+            if done[root] {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            on_stack[root] = true;
+            while let Some((bag, next)) = stack.last_mut() {
+                let bag = *bag;
+                match self.contents[bag].get(*next) {
+                    Some(&(_, inner)) => {
+                        *next += 1;
+                        if on_stack[inner] {
+                            let start = stack.iter().position(|&(b, _)| b == inner).unwrap();
+                            let cycle = stack[start..].iter().map(|&(b, _)| b).chain(Some(inner));
+                            return Err(GraphError::Cycle(
+                                cycle.map(|b| self.names[b].clone()).collect(),
+                            ));
+                        }
+                        if !done[inner] {
+                            on_stack[inner] = true;
+                            stack.push((inner, 0));
+                        }
+                    }
+                    None => {
+                        on_stack[bag] = false;
+                        done[bag] = true;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // All bags that can eventually contain `bag`, sorted by name
+    pub(crate) fn containers(&self, bag: &str) -> Result<Vec<&str>, GraphError> {
+        let mut seen = vec![false; self.names.len()];
+        let mut queue = vec![self.id(bag)?];
+        while let Some(bag) = queue.pop() {
+            for &outer in &self.containers[bag] {
+                if !std::mem::replace(&mut seen[outer], true) {
+                    queue.push(outer);
+                }
+            }
+        }
+        let mut containers = (0..self.names.len())
+            .filter(|&b| seen[b])
+            .map(|b| self.names[b].as_str())
+            .collect::<Vec<_>>();
+        containers.sort_unstable();
+        Ok(containers)
+    }
+
+    // Only counts the bags that `bag` contains, so overflowing counts
+    // elsewhere in the graph do not matter.
+    pub(crate) fn count_inside(&self, bag: &str) -> Result<usize, GraphError> {
+        let root = self.id(bag)?;
+        let mut inside = vec![None::<usize>; self.names.len()];
+        let mut stack = vec![(root, 0, 0)];
+        while let Some((bag, next, count)) = stack.last_mut() {
+            match self.contents[*bag].get(*next) {
+                Some(&(amt, inner)) => match inside[inner] {
+                    Some(n) => {
+                        *count = n
+                            .checked_add(1)
+                            .and_then(|n| n.checked_mul(amt))
+                            .and_then(|n| n.checked_add(*count))
+                            .ok_or(GraphError::Overflow)?;
+                        *next += 1;
+                    }
+                    None => stack.push((inner, 0, 0)),
+                },
+                None => {
+                    inside[*bag] = Some(*count);
+                    stack.pop();
+                }
+            }
+        }
+        Ok(inside[root].unwrap())
+    }
+
+    // Graphviz DOT of the whole graph, or only of `root` and what it contains
+    pub(crate) fn to_dot(&self, root: Option<&str>) -> Result<String, GraphError> {
+        let mut include = vec![root.is_none(); self.names.len()];
+        if let Some(root) = root {
+            let mut queue = vec![self.id(root)?];
+            while let Some(bag) = queue.pop() {
+                if !std::mem::replace(&mut include[bag], true) {
+                    queue.extend(self.contents[bag].iter().map(|&(_, inner)| inner));
                 }
-                keys.push(color.as_str());
             }
         }
+
+        let mut dot = String::from("digraph bags {\n");
+        for bag in (0..self.names.len()).filter(|&b| include[b]) {
+            dot.push_str(&format!("    \"{}\";\n", self.names[bag]));
+            for &(amt, inner) in &self.contents[bag] {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label={}];\n",
+                    self.names[bag], self.names[inner], amt
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
     }
-    total
 }
 
-fn run2(input: HashMap<String, Vec<(usize, String)>>) -> usize {
-    let mut total = 0;
-    let mut q = vec![(1, "shiny gold")];
-    while !q.is_empty() {
-        let (mult, next) = q.pop().unwrap();
-        for (amt, color) in &input[next] {
-            let amt = *amt * mult;
-            total += amt;
-            q.push((amt, color.as_str()));
+const ADJECTIVES: [&str; 16] = [
+    "light", "dark", "bright", "muted", "shiny", "faded", "dotted", "vibrant", "pale", "posh",
+    "drab", "dim", "clear", "wavy", "plaid", "striped",
+];
+
+const COLORS: [&str; 16] = [
+    "red", "orange", "white", "yellow", "gold", "olive", "plum", "blue", "black", "green", "teal",
+    "tan", "cyan", "lime", "coral", "violet",
+];
+
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 8;
+
+    // Bags are placed in `size` layers and only contain bags from the next
+    // layer, with shiny gold in the middle one. This keeps the graph acyclic
+    // and lets both answers be counted layer by layer.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        const WIDTH: usize = 8;
+        // shiny gold is placed separately, so one name less is available
+        let max_size = (ADJECTIVES.len() * COLORS.len() - 1) / WIDTH;
+        assert!(
+            (1..=max_size).contains(&size),
+            "depth must be between 1 and {}",
+            max_size
+        );
+
+        let mut names = ADJECTIVES
+            .iter()
+            .flat_map(|a| COLORS.iter().map(move |c| format!("{} {}", a, c)))
+            .filter(|n| n != "shiny gold")
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut names);
+        let mut names = names.into_iter();
+        let mut layers = (0..size)
+            .map(|_| names.by_ref().take(WIDTH).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let gold = size / 2;
+        layers[gold][0] = String::from("shiny gold");
+
+        let mut rules = Vec::new();
+        // `None` once the count does not fit into a usize
+        let mut inside = vec![vec![Some(0_usize); WIDTH]; size];
+        let mut holds_gold = vec![vec![false; WIDTH]; size];
+        for layer in (0..size).rev() {
+            for bag in 0..WIDTH {
+                let mut inner = Vec::new();
+                if layer + 1 < size {
+                    let mut children = (0..WIDTH).collect::<Vec<_>>();
+                    rng.shuffle(&mut children);
+                    for &child in &children[..rng.range(1..=3) as usize] {
+                        let amt = rng.range(1..=3) as usize;
+                        inside[layer][bag] = inside[layer + 1][child]
+                            .and_then(|n| n.checked_add(1))
+                            .and_then(|n| n.checked_mul(amt))
+                            .zip(inside[layer][bag])
+                            .and_then(|(n, count)| n.checked_add(count));
+                        holds_gold[layer][bag] |=
+                            holds_gold[layer + 1][child] || (layer + 1 == gold && child == 0);
+                        inner.push((amt, layers[layer + 1][child].clone()));
+                    }
+                }
+                rules.push(Rule {
+                    outer: layers[layer][bag].clone(),
+                    inner,
+                });
+            }
+        }
+        rng.shuffle(&mut rules);
+
+        Generated {
+            input: rules.iter().map(|r| format!("{}\n", r)).collect(),
+            part1: Some(
+                holds_gold
+                    .iter()
+                    .flatten()
+                    .filter(|h| **h)
+                    .count()
+                    .to_string(),
+            ),
+            part2: inside[gold][0].map(|n| n.to_string()),
         }
     }
-    total
 }
 
 #[derive(Clone, Debug, Default)]
@@ -80,18 +314,60 @@ impl From<String> for Rule {
     }
 }
 
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bags contain ", self.outer)?;
+        if self.inner.is_empty() {
+            return f.write_str("no other bags.");
+        }
+        for (i, (amt, color)) in self.inner.iter().enumerate() {
+            let sep = if i + 1 == self.inner.len() { "." } else { "," };
+            let bags = if *amt == 1 { "bag" } else { "bags" };
+            write!(
+                f,
+                "{}{} {} {}{}",
+                if i == 0 { "" } else { " " },
+                amt,
+                color,
+                bags,
+                sep
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day7, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 222);
         assert_eq!(res2, 13264);
     }
 
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
+    #[test]
+    fn test_generated_deep() {
+        // The outermost bags hold too many bags to count.
+        let generated = Solver::generate(&mut Rng::new(2020), 31);
+        assert_eq!(generated.input.lines().count(), 31 * 8);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
     #[test]
     fn test_ex1() {
         let input = "
@@ -115,6 +391,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_graph() {
+        let rules = "
+            light red bags contain 1 bright white bag, 2 muted yellow bags.
+            bright white bags contain 1 shiny gold bag.
+            muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+            shiny gold bags contain 3 faded blue bags.
+        ";
+        let graph = BagGraph::new(Solver::parse_input(rules)).unwrap();
+        assert_eq!(
+            graph.containers("faded blue").unwrap(),
+            vec!["bright white", "light red", "muted yellow", "shiny gold"]
+        );
+        assert_eq!(graph.containers("light red").unwrap(), Vec::<&str>::new());
+        assert_eq!(graph.count_inside("faded blue"), Ok(0));
+        assert_eq!(graph.count_inside("muted yellow"), Ok(17));
+        assert_eq!(
+            graph.count_inside("plaid magenta"),
+            Err(GraphError::UnknownBag(String::from("plaid magenta")))
+        );
+        assert_eq!(
+            graph.to_dot(Some("bright white")).unwrap(),
+            concat!(
+                "digraph bags {\n",
+                "    \"bright white\";\n",
+                "    \"bright white\" -> \"shiny gold\" [label=1];\n",
+                "    \"shiny gold\";\n",
+                "    \"shiny gold\" -> \"faded blue\" [label=3];\n",
+                "    \"faded blue\";\n",
+                "}\n",
+            )
+        );
+        assert_eq!(graph.to_dot(None).unwrap().matches(" -> ").count(), 6);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let rules = "
+            light red bags contain 1 bright white bag.
+            bright white bags contain 1 shiny gold bag, 2 faded blue bags.
+            shiny gold bags contain 3 light red bags.
+        ";
+        let err = BagGraph::new(Solver::parse_input(rules)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bags contain each other: light red -> bright white -> shiny gold -> light red"
+        );
+    }
+
+    #[test]
+    fn test_deep() {
+        let chain = (0..200_000)
+            .map(|i| format!("dark c{} bags contain 1 dark c{} bag.", i, i + 1))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let graph = BagGraph::new(Solver::parse_input(&chain)).unwrap();
+        assert_eq!(graph.count_inside("dark c0"), Ok(200_000));
+        assert_eq!(graph.containers("dark c200000").unwrap().len(), 200_000);
+
+        let doubling = (0..70)
+            .map(|i| format!("dark d{} bags contain 2 dark d{} bags.", i, i + 1))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let graph = BagGraph::new(Solver::parse_input(&doubling)).unwrap();
+        assert_eq!(graph.containers("dark d70").unwrap().len(), 70);
+        assert_eq!(graph.count_inside("dark d60"), Ok(2046));
+        assert_eq!(graph.count_inside("dark d0"), Err(GraphError::Overflow));
+    }
+
     #[test]
     fn test_ex2() {
         let input = "
@@ -1,88 +1,913 @@
-use std::collections::HashSet;
+use aoc2020::{Generated, Generator, Rng};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt,
+    io::{BufRead, Write},
+    iter,
+};
 
 register!(
     "input/day8.txt";
-    run(input: Op) -> i32 {
-        (run1(&input), run2(input))
+    run(input: verbatim aoc2020::Raw) -> i64 {
+        let program = Program::handheld(&input).unwrap_or_else(|e| panic!("{}", e));
+        (run1(&program), run2(&program))
     }
 );
 
-fn run1(input: &[Op]) -> i32 {
-    let mut seen = HashSet::new();
-    let mut acc = 0;
-    let mut cursor = 0;
-    loop {
-        if !seen.insert(cursor) {
-            return acc;
+fn run1(program: &Program) -> i64 {
+    let mut vm = Vm::new(program);
+    match vm.run() {
+        State::Loop(_) => vm.registers()[0],
+        state => panic!("expected a loop, but got {:?}", state),
+    }
+}
+
+fn run2(program: &Program) -> i64 {
+    match repairs(program).unwrap_or_else(|e| panic!("{}", e))[..] {
+        [repair, ..] => repair.acc,
+        [] => panic!("no solution found"),
+    }
+}
+
+const MAX_OPERANDS: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Operand {
+    Immediate,
+    Register,
+    // an immediate or a register
+    Value,
+    // a relative jump, written as an immediate or a label
+    Offset,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Arg {
+    Imm(i64),
+    Reg(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Next,
+    Jump(i64),
+    Halt,
+}
+
+// Whether an instruction only ever jumps based on its operands, or whether
+// it may branch on the registers as well.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Control {
+    Fixed,
+    Conditional,
+}
+
+pub(crate) struct Cpu<'a> {
+    registers: &'a mut [i64],
+    args: &'a [Arg],
+}
+
+impl Cpu<'_> {
+    pub(crate) fn get(&self, operand: usize) -> i64 {
+        match self.args[operand] {
+            Arg::Imm(value) => value,
+            Arg::Reg(reg) => self.registers[reg],
+        }
+    }
+
+    pub(crate) fn register(&mut self, operand: usize) -> &mut i64 {
+        match self.args[operand] {
+            Arg::Reg(reg) => &mut self.registers[reg],
+            Arg::Imm(_) => panic!("operand {} is not a register", operand),
+        }
+    }
+
+    pub(crate) fn acc(&mut self) -> &mut i64 {
+        &mut self.registers[0]
+    }
+}
+
+pub(crate) type Semantics = fn(&mut Cpu<'_>) -> Flow;
+
+#[derive(Clone)]
+struct Opcode {
+    name: String,
+    operands: Vec<Operand>,
+    control: Control,
+    exec: Semantics,
+}
+
+// The first register is the accumulator.
+#[derive(Clone)]
+pub(crate) struct InstructionSet {
+    registers: Vec<String>,
+    opcodes: Vec<Opcode>,
+}
+
+impl InstructionSet {
+    pub(crate) fn new(registers: &[&str]) -> Self {
+        assert!(!registers.is_empty(), "the accumulator is required");
+        Self {
+            registers: registers.iter().map(|r| r.to_string()).collect(),
+            opcodes: Vec::new(),
         }
-        match input[cursor as usize] {
-            Op::Nop(_) => cursor += 1,
-            Op::Jmp(amt) => cursor += amt,
-            Op::Acc(amt) => {
-                acc += amt;
-                cursor += 1;
+    }
+
+    // The instructions of the handheld console from the puzzle
+    pub(crate) fn handheld() -> Self {
+        let mut isa = Self::new(&["acc"]);
+        isa.define(&[
+            ("nop", &[Operand::Immediate], Control::Fixed, |_| Flow::Next),
+            ("acc", &[Operand::Immediate], Control::Fixed, |cpu| {
+                let value = cpu.get(0);
+                *cpu.acc() = cpu.acc().wrapping_add(value);
+                Flow::Next
+            }),
+            ("jmp", &[Operand::Offset], Control::Fixed, |cpu| {
+                Flow::Jump(cpu.get(0))
+            }),
+        ]);
+        isa
+    }
+
+    // The handheld instructions with more registers, arithmetic,
+    // a conditional jump and a halt instruction. Arithmetic wraps around.
+    pub(crate) fn extended() -> Self {
+        use Operand::*;
+
+        let mut isa = Self::new(&["acc", "a", "b", "c", "d"]);
+        isa.define(&[
+            ("nop", &[Immediate], Control::Fixed, |_| Flow::Next),
+            ("acc", &[Value], Control::Fixed, |cpu| {
+                let value = cpu.get(0);
+                *cpu.acc() = cpu.acc().wrapping_add(value);
+                Flow::Next
+            }),
+            ("jmp", &[Offset], Control::Fixed, |cpu| {
+                Flow::Jump(cpu.get(0))
+            }),
+            ("hlt", &[], Control::Fixed, |_| Flow::Halt),
+            ("set", &[Register, Value], Control::Fixed, |cpu| {
+                *cpu.register(0) = cpu.get(1);
+                Flow::Next
+            }),
+            ("add", &[Register, Value], Control::Fixed, |cpu| {
+                let value = cpu.get(1);
+                let reg = cpu.register(0);
+                *reg = reg.wrapping_add(value);
+                Flow::Next
+            }),
+            ("mul", &[Register, Value], Control::Fixed, |cpu| {
+                let value = cpu.get(1);
+                let reg = cpu.register(0);
+                *reg = reg.wrapping_mul(value);
+                Flow::Next
+            }),
+            ("jnz", &[Value, Offset], Control::Conditional, |cpu| {
+                if cpu.get(0) == 0 {
+                    Flow::Next
+                } else {
+                    Flow::Jump(cpu.get(1))
+                }
+            }),
+        ]);
+        isa
+    }
+
+    fn define(&mut self, opcodes: &[(&str, &[Operand], Control, Semantics)]) {
+        for &(name, operands, control, exec) in opcodes {
+            self.register(name, operands, control, exec).unwrap();
+        }
+    }
+
+    pub(crate) fn register(
+        &mut self,
+        name: &str,
+        operands: &[Operand],
+        control: Control,
+        exec: Semantics,
+    ) -> Result<(), String> {
+        if self.opcode(name).is_some() {
+            return Err(format!("instruction {} is already defined", name));
+        }
+        if operands.len() > MAX_OPERANDS {
+            return Err(format!(
+                "instructions take at most {} operands",
+                MAX_OPERANDS
+            ));
+        }
+        self.opcodes.push(Opcode {
+            name: name.to_string(),
+            operands: operands.to_vec(),
+            control,
+            exec,
+        });
+        Ok(())
+    }
+
+    fn opcode(&self, name: &str) -> Option<usize> {
+        self.opcodes.iter().position(|op| op.name == name)
+    }
+
+    fn reg(&self, name: &str) -> Option<usize> {
+        self.registers.iter().position(|r| r == name)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Instruction {
+    opcode: usize,
+    len: usize,
+    args: [Arg; MAX_OPERANDS],
+}
+
+impl Instruction {
+    pub(crate) fn args(&self) -> &[Arg] {
+        &self.args[..self.len]
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Program {
+    isa: InstructionSet,
+    code: Vec<Instruction>,
+}
+
+impl Program {
+    pub(crate) fn handheld(source: &str) -> Result<Self, String> {
+        Self::assemble(InstructionSet::handheld(), source)
+    }
+
+    // One instruction per line, operands separated by whitespace or commas.
+    // Comments start with `;` or `#`, labels are written as `name:` and can
+    // be used in place of jump offsets.
+    pub(crate) fn assemble(isa: InstructionSet, source: &str) -> Result<Self, String> {
+        let mut code = Vec::new();
+        let mut labels = HashMap::new();
+        let mut fixups = Vec::new();
+
+        for (line_no, line) in source.lines().enumerate() {
+            let error = |msg: String| format!("line {}: {}", line_no + 1, msg);
+            let mut line = line
+                .split([';', '#'].as_ref())
+                .next()
+                .unwrap_or_default()
+                .trim();
+            while let Some((label, rest)) = line.split_once(':') {
+                let label = label.trim();
+                if !is_identifier(label) {
+                    break;
+                }
+                if labels.insert(label.to_string(), code.len()).is_some() {
+                    return Err(error(format!("duplicate label {}", label)));
+                }
+                line = rest.trim();
             }
+
+            let mut tokens = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty());
+            let name = match tokens.next() {
+                Some(name) => name,
+                None => continue,
+            };
+            let opcode = isa
+                .opcode(name)
+                .ok_or_else(|| error(format!("unknown instruction {}", name)))?;
+            let operands = &isa.opcodes[opcode].operands;
+            let tokens = tokens.collect::<Vec<_>>();
+            if tokens.len() != operands.len() {
+                return Err(error(format!(
+                    "{} takes {} operands, got {}",
+                    name,
+                    operands.len(),
+                    tokens.len()
+                )));
+            }
+
+            let mut args = [Arg::Imm(0); MAX_OPERANDS];
+            for (i, (&operand, token)) in operands.iter().zip(tokens).enumerate() {
+                let imm = token.parse::<i64>().ok().map(Arg::Imm);
+                let reg = isa.reg(token).map(Arg::Reg);
+                args[i] = match (operand, imm, reg) {
+                    (Operand::Immediate, Some(imm), _) => imm,
+                    (Operand::Register, _, Some(reg)) => reg,
+                    (Operand::Value, imm, reg) if imm.or(reg).is_some() => imm.or(reg).unwrap(),
+                    (Operand::Offset, Some(imm), _) => imm,
+                    (Operand::Offset, None, _) if is_identifier(token) => {
+                        fixups.push((code.len(), i, token, line_no));
+                        Arg::Imm(0)
+                    }
+                    _ => {
+                        return Err(error(format!(
+                            "invalid operand {} for {}, expected {:?}",
+                            token, name, operand
+                        )))
+                    }
+                };
+            }
+            code.push(Instruction {
+                opcode,
+                len: operands.len(),
+                args,
+            });
         }
+
+        for (pc, i, label, line_no) in fixups {
+            let target = labels
+                .get(label)
+                .ok_or_else(|| format!("line {}: unknown label {}", line_no + 1, label))?;
+            code[pc].args[i] = Arg::Imm(*target as i64 - pc as i64);
+        }
+        Ok(Self { isa, code })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub(crate) fn instructions(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    fn name(&self, instruction: &Instruction) -> &str {
+        &self.isa.opcodes[instruction.opcode].name
+    }
+
+    pub(crate) fn display(&self, instruction: &Instruction) -> String {
+        let operands = &self.isa.opcodes[instruction.opcode].operands;
+        iter::once(self.name(instruction).to_string())
+            .chain(
+                instruction
+                    .args()
+                    .iter()
+                    .zip(operands)
+                    .map(|(arg, operand)| match (arg, operand) {
+                        (Arg::Reg(reg), _) => self.isa.registers[*reg].clone(),
+                        (Arg::Imm(imm), Operand::Value) => imm.to_string(),
+                        (Arg::Imm(imm), _) => format!("{:+}", imm),
+                    }),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 }
 
-fn run2(mut input: Vec<Op>) -> i32 {
-    for i in 0..input.len() {
-        let op = input[i];
-        match op {
-            Op::Nop(amt) => input[i] = Op::Jmp(amt),
-            Op::Jmp(amt) => input[i] = Op::Nop(amt),
-            _ => {}
+// Writes the program back in a form that assembles to the same program.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.code
+            .iter()
+            .try_for_each(|instruction| writeln!(f, "{}", self.display(instruction)))
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Repair {
+    pub(crate) pc: usize,
+    pub(crate) instruction: Instruction,
+    pub(crate) acc: i64,
+}
+
+// Finds every `nop`/`jmp` flip that makes a looping program terminate in
+// linear time. Walking the jumps backwards from the end marks everything
+// that terminates unchanged, then a single run of the original program
+// checks where each flip would lead. A flip cannot lead back to itself,
+// as that would mean that the original program terminates.
+pub(crate) fn repairs(program: &Program) -> Result<Vec<Repair>, String> {
+    let (nop, acc, jmp) = match ["nop", "acc", "jmp"].map(|op| program.isa.opcode(op)) {
+        [Some(nop), Some(acc), Some(jmp)] => (nop, acc, jmp),
+        _ => return Err(String::from("repairs need nop, acc and jmp instructions")),
+    };
+    let amount = |instruction: &Instruction| match instruction.args() {
+        [Arg::Imm(amt)] if [nop, acc, jmp].contains(&instruction.opcode) => Ok(*amt),
+        _ => Err(format!(
+            "repairs only work with nop, acc and jmp, got {}",
+            program.display(instruction)
+        )),
+    };
+    let target = |pc: usize, instruction: &Instruction| {
+        let target = pc as i64
+            + if instruction.opcode == jmp {
+                amount(instruction).ok()?
+            } else {
+                1
+            };
+        (0..=program.len() as i64)
+            .contains(&target)
+            .then_some(target as usize)
+    };
+
+    let mut sources = vec![Vec::new(); program.len() + 1];
+    for (pc, instruction) in program.code.iter().enumerate() {
+        let _ = amount(instruction)?;
+        if let Some(target) = target(pc, instruction) {
+            sources[target].push(pc);
         }
-        if let Some(amt) = try_run2(&input) {
-            return amt;
+    }
+
+    // the accumulator gained on the way from each instruction to the end
+    let mut to_end = vec![None; program.len() + 1];
+    to_end[program.len()] = Some(0);
+    let mut queue = vec![program.len()];
+    while let Some(target) = queue.pop() {
+        let total = to_end[target].unwrap_or_default();
+        for &pc in &sources[target] {
+            let instruction = &program.code[pc];
+            let gain = if instruction.opcode == acc {
+                amount(instruction)?
+            } else {
+                0
+            };
+            to_end[pc] = Some(total + gain);
+            queue.push(pc);
         }
-        input[i] = op;
     }
-    panic!("no solution found");
+    if to_end[0].is_some() {
+        return Ok(Vec::new());
+    }
+
+    // Without conditional jumps every instruction runs at most once.
+    let mut vm = Vm::with_trace_limit(program, program.len());
+    let _ = vm.run();
+    Ok(vm
+        .trace()
+        .iter()
+        .filter_map(|step| {
+            let flipped = match step.instruction.opcode {
+                op if op == nop => jmp,
+                op if op == jmp => nop,
+                _ => return None,
+            };
+            let instruction = Instruction {
+                opcode: flipped,
+                ..step.instruction
+            };
+            let rest = to_end[target(step.pc, &instruction)?]?;
+            Some(Repair {
+                pc: step.pc,
+                instruction,
+                acc: step.registers[0] + rest,
+            })
+        })
+        .collect())
 }
 
-fn try_run2(input: &[Op]) -> Option<i32> {
-    let mut seen = HashSet::new();
-    let mut acc = 0;
-    let mut cursor = 0;
-    loop {
-        if cursor == input.len() as i32 {
-            return Some(acc);
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 1000;
+
+    // The repaired program runs every instruction exactly once. The first
+    // `split` instructions run first and end in the corrupted one, which
+    // jumps back among them. Any other flip in there stays within them as
+    // well, so there is exactly one repair.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        assert!(size > 0, "programs need at least one instruction");
+        let split = rng.range(1..=size as u64) as usize;
+        let mut program = vec![(String::from("nop"), 0_i64); size];
+
+        let mut prefix = (1..split.saturating_sub(1)).collect::<Vec<_>>();
+        rng.shuffle(&mut prefix);
+        let prefix = iter::once(0)
+            .chain(prefix)
+            .chain(Some(split - 1).filter(|&c| c > 0))
+            .collect::<Vec<_>>();
+        let mut suffix = (split + 1..size).collect::<Vec<_>>();
+        rng.shuffle(&mut suffix);
+        let suffix = iter::once(split)
+            .chain(suffix)
+            .chain(iter::once(size))
+            .collect::<Vec<_>>();
+
+        let mut link = |from: usize, to: usize, nop_range: u64| {
+            program[from] = if to != from + 1 {
+                (String::from("jmp"), to as i64 - from as i64)
+            } else if rng.chance(1, 4) {
+                (
+                    String::from("nop"),
+                    rng.below(nop_range) as i64 - from as i64,
+                )
+            } else {
+                (String::from("acc"), rng.range(0..=100) as i64 - 50)
+            }
+        };
+        for pair in prefix.windows(2) {
+            link(pair[0], pair[1], split as u64);
+        }
+        if split < size {
+            for pair in suffix.windows(2) {
+                link(pair[0], pair[1], size as u64);
+            }
+        }
+        let corrupted = split - 1;
+        program[corrupted] = (
+            String::from("jmp"),
+            rng.below(split as u64) as i64 - corrupted as i64,
+        );
+
+        let acc = |ops: &[(String, i64)]| -> i64 {
+            ops.iter()
+                .filter(|(op, _)| op == "acc")
+                .map(|(_, amt)| amt)
+                .sum()
+        };
+        Generated {
+            input: program
+                .iter()
+                .map(|(op, amt)| format!("{} {:+}\n", op, amt))
+                .collect(),
+            part1: Some(acc(&program[..split]).to_string()),
+            part2: Some(acc(&program).to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum State {
+    Running,
+    // the program counter is right after the last instruction
+    Terminated,
+    // a halt instruction was executed
+    Halted,
+    // the program counter left the program anywhere else
+    OutOfBounds(i64),
+    Breakpoint(usize),
+    // the instructions of the loop, in execution order
+    Loop(Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Step {
+    pub(crate) pc: usize,
+    pub(crate) instruction: Instruction,
+    pub(crate) registers: Vec<i64>,
+}
+
+fn execute(program: &Program, pc: usize, registers: &mut [i64]) -> Flow {
+    let instruction = &program.code[pc];
+    let mut cpu = Cpu {
+        registers,
+        args: instruction.args(),
+    };
+    (program.isa.opcodes[instruction.opcode].exec)(&mut cpu)
+}
+
+// Without conditional jumps the machine loops as soon as it would execute
+// an instruction for the second time. Otherwise, it has to see the same
+// instruction with the same registers again, within the last
+// `HISTORY_LIMIT` steps.
+enum Seen {
+    Pc(Vec<Option<usize>>),
+    State(
+        HashMap<(usize, Vec<i64>), usize>,
+        VecDeque<(usize, Vec<i64>)>,
+    ),
+}
+
+const HISTORY_LIMIT: usize = 100_000;
+
+// Only the last steps are traced, so that a program that never stops
+// does not use up all memory.
+pub(crate) const TRACE_LIMIT: usize = 10_000;
+
+pub(crate) struct Vm<'a> {
+    program: &'a Program,
+    pc: i64,
+    registers: Vec<i64>,
+    halted: bool,
+    seen: Seen,
+    steps: usize,
+    trace: VecDeque<Step>,
+    trace_limit: usize,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<'a> Vm<'a> {
+    pub(crate) fn new(program: &'a Program) -> Self {
+        Self::with_trace_limit(program, TRACE_LIMIT)
+    }
+
+    pub(crate) fn with_trace_limit(program: &'a Program, trace_limit: usize) -> Self {
+        let conditional = program
+            .code
+            .iter()
+            .any(|i| program.isa.opcodes[i.opcode].control == Control::Conditional);
+        Self {
+            program,
+            pc: 0,
+            registers: vec![0; program.isa.registers.len()],
+            halted: false,
+            seen: if conditional {
+                Seen::State(HashMap::new(), VecDeque::new())
+            } else {
+                Seen::Pc(vec![None; program.len()])
+            },
+            steps: 0,
+            trace: VecDeque::new(),
+            trace_limit,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn pc(&self) -> i64 {
+        self.pc
+    }
+
+    pub(crate) fn registers(&self) -> &[i64] {
+        &self.registers
+    }
+
+    pub(crate) fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // The last executed steps, at most as many as the trace limit.
+    pub(crate) fn trace(&self) -> &VecDeque<Step> {
+        &self.trace
+    }
+
+    pub(crate) fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub(crate) fn add_breakpoint(&mut self, pc: usize) {
+        let _ = self.breakpoints.insert(pc);
+    }
+
+    pub(crate) fn remove_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    // What the machine would do next, without executing anything
+    pub(crate) fn state(&self) -> State {
+        let len = self.program.len() as i64;
+        if self.halted {
+            return State::Halted;
+        }
+        if self.pc == len {
+            return State::Terminated;
+        }
+        if !(0..len).contains(&self.pc) {
+            return State::OutOfBounds(self.pc);
+        }
+        let pc = self.pc as usize;
+        let start = match &self.seen {
+            Seen::Pc(executed_at) => executed_at[pc],
+            Seen::State(seen, _) => seen.get(&(pc, self.registers.clone())).copied(),
+        };
+        match start {
+            Some(start) => State::Loop(self.loop_from(start)),
+            None => State::Running,
         }
-        if !seen.insert(cursor) {
-            return None;
+    }
+
+    pub(crate) fn step(&mut self) -> State {
+        let state = self.state();
+        if state != State::Running {
+            return state;
+        }
+
+        let pc = self.pc as usize;
+        match &mut self.seen {
+            Seen::Pc(executed_at) => executed_at[pc] = Some(self.steps),
+            Seen::State(seen, order) => {
+                if order.len() == HISTORY_LIMIT {
+                    let _ = seen.remove(&order.pop_front().unwrap());
+                }
+                let _ = seen.insert((pc, self.registers.clone()), self.steps);
+                order.push_back((pc, self.registers.clone()));
+            }
+        }
+
+        let instruction = self.program.code[pc];
+        // a jump that does not fit lands out of bounds anyway
+        match execute(self.program, pc, &mut self.registers) {
+            Flow::Next => self.pc += 1,
+            Flow::Jump(offset) => self.pc = self.pc.saturating_add(offset),
+            Flow::Halt => self.halted = true,
+        }
+        self.steps += 1;
+        if self.trace.len() == self.trace_limit {
+            let _ = self.trace.pop_front();
+        }
+        if self.trace_limit > 0 {
+            self.trace.push_back(Step {
+                pc,
+                instruction,
+                registers: self.registers.clone(),
+            });
+        }
+        self.state()
+    }
+
+    // The pcs of the loop that started at step `start` and that the machine
+    // is about to enter again.
+    fn loop_from(&self, start: usize) -> Vec<usize> {
+        let dropped = self.steps - self.trace.len();
+        if start >= dropped {
+            return self.trace.range(start - dropped..).map(|s| s.pc).collect();
+        }
+        // The start is no longer traced, so the loop is run once more on
+        // a copy of the registers.
+        let by_pc = matches!(self.seen, Seen::Pc(_));
+        let mut registers = self.registers.clone();
+        let mut pc = self.pc;
+        let mut pcs = Vec::new();
+        loop {
+            pcs.push(pc as usize);
+            match execute(self.program, pc as usize, &mut registers) {
+                Flow::Next => pc += 1,
+                Flow::Jump(offset) => pc = pc.saturating_add(offset),
+                Flow::Halt => break pcs,
+            }
+            if pc == self.pc && (by_pc || registers == self.registers) {
+                break pcs;
+            }
         }
-        match input[cursor as usize] {
-            Op::Nop(_) => cursor += 1,
-            Op::Jmp(amt) => cursor += amt,
-            Op::Acc(amt) => {
-                acc += amt;
-                cursor += 1;
+    }
+
+    // Runs until the machine stops or `stop` returns true. Breakpoints
+    // are not checked before the first step, so that a run can continue
+    // from the breakpoint it stopped at.
+    pub(crate) fn run_until(&mut self, mut stop: impl FnMut(&Self) -> bool) -> State {
+        let mut state = self.step();
+        while state == State::Running {
+            if self.breakpoints.contains(&(self.pc as usize)) {
+                return State::Breakpoint(self.pc as usize);
             }
+            if stop(self) {
+                break;
+            }
+            state = self.step();
         }
+        state
+    }
+
+    pub(crate) fn run(&mut self) -> State {
+        self.run_until(|_| false)
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum Op {
-    Nop(i32),
-    Acc(i32),
-    Jmp(i32),
+// Lists the program with addresses and resolved jump targets.
+pub(crate) fn disassemble(program: &Program) -> String {
+    let width = program.len().saturating_sub(1).to_string().len();
+    program
+        .code
+        .iter()
+        .enumerate()
+        .map(|(pc, instruction)| {
+            let operands = &program.isa.opcodes[instruction.opcode].operands;
+            let targets = instruction
+                .args()
+                .iter()
+                .zip(operands)
+                .filter_map(|(arg, operand)| match (arg, operand) {
+                    (Arg::Imm(offset), Operand::Offset) => Some(pc as i64 + offset),
+                    _ => None,
+                })
+                .map(|target| format!(" ; -> {}", target))
+                .collect::<String>();
+            let text = program.display(instruction);
+            if targets.is_empty() {
+                format!("{:>width$}  {}\n", pc, text, width = width)
+            } else {
+                format!("{:>width$}  {:<8}{}\n", pc, text, targets, width = width)
+            }
+        })
+        .collect()
 }
 
-impl From<String> for Op {
-    fn from(input: String) -> Self {
-        let mut parts = input.split_ascii_whitespace();
-        let op = parts.next().unwrap();
-        let amt = parts.next().unwrap().parse::<i32>().unwrap();
-        match op {
-            "nop" => Op::Nop(amt),
-            "acc" => Op::Acc(amt),
-            "jmp" => Op::Jmp(amt),
-            op => unreachable!("op : {}", op),
+const HELP: &str = "\
+commands:
+  s [n]   step n instructions (default 1)
+  c       continue until a breakpoint, a loop or the end of the program,
+          for at most 100000 steps
+  b <pc>  set a breakpoint
+  d <pc>  delete a breakpoint
+  l       list the program around the program counter
+  t       show the last steps of the execution trace
+  p       print the machine state
+  q       quit
+";
+
+const CONTINUE_LIMIT: usize = 100_000;
+
+// A line based debugger, reading commands from `input` until it ends or
+// the user quits.
+pub(crate) fn debug(
+    program: &Program,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    let mut vm = Vm::new(program);
+    let listing = disassemble(program);
+    write!(out, "{}", HELP)?;
+    for line in input.lines() {
+        let line = line?;
+        let mut parts = line.split_whitespace();
+        let arg = |arg: Option<&str>| arg.and_then(|a| a.parse::<usize>().ok());
+        match (parts.next(), arg(parts.next())) {
+            (Some("s"), steps) => {
+                let mut state = State::Running;
+                for _ in 0..steps.unwrap_or(1) {
+                    state = vm.step();
+                    if state != State::Running {
+                        break;
+                    }
+                }
+                report(&vm, &state, &mut out)?;
+            }
+            (Some("c"), _) => {
+                let limit = vm.steps() + CONTINUE_LIMIT;
+                let state = vm.run_until(|vm| vm.steps() >= limit);
+                report(&vm, &state, &mut out)?;
+            }
+            (Some("b"), Some(pc)) => {
+                vm.add_breakpoint(pc);
+                writeln!(out, "breakpoint at {}", pc)?;
+            }
+            (Some("d"), Some(pc)) => {
+                if vm.remove_breakpoint(pc) {
+                    writeln!(out, "deleted breakpoint at {}", pc)?;
+                } else {
+                    writeln!(out, "no breakpoint at {}", pc)?;
+                }
+            }
+            (Some("l"), _) => {
+                let pc = vm.pc().max(0) as usize;
+                for (i, line) in listing
+                    .lines()
+                    .enumerate()
+                    .skip(pc.saturating_sub(3))
+                    .take(7)
+                {
+                    let marker = if i == pc { '>' } else { ' ' };
+                    let bp = if vm.breakpoints().any(|b| b == i) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    writeln!(out, "{}{} {}", marker, bp, line)?;
+                }
+            }
+            (Some("t"), _) => {
+                for step in vm.trace() {
+                    writeln!(
+                        out,
+                        "{:>5}  {:<12} {}",
+                        step.pc,
+                        program.display(&step.instruction),
+                        registers(program, &step.registers)
+                    )?;
+                }
+            }
+            (Some("p"), _) => report(&vm, &vm.state(), &mut out)?,
+            (Some("q"), _) => break,
+            (None, _) => {}
+            _ => write!(out, "{}", HELP)?,
         }
     }
+    Ok(())
+}
+
+fn registers(program: &Program, values: &[i64]) -> String {
+    program
+        .isa
+        .registers
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn report(vm: &Vm<'_>, state: &State, out: &mut impl Write) -> std::io::Result<()> {
+    let state = match state {
+        State::Running => String::from("running"),
+        State::Terminated => String::from("terminated"),
+        State::Halted => String::from("halted"),
+        State::OutOfBounds(pc) => format!("jumped out of the program to {}", pc),
+        State::Breakpoint(pc) => format!("stopped at breakpoint {}", pc),
+        State::Loop(pcs) => format!(
+            "loop detected: {}",
+            pcs.iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        ),
+    };
+    writeln!(
+        out,
+        "pc={} {} steps={}: {}",
+        vm.pc(),
+        registers(vm.program, vm.registers()),
+        vm.steps(),
+        state
+    )
 }
 
 #[cfg(test)]
@@ -90,7 +915,20 @@ mod tests {
     use super::*;
     use aoc2020::Solution;
 
+    const EX: &str = "
+        nop +0
+        acc +1
+        jmp +4
+        acc +3
+        jmp -3
+        acc -99
+        acc +1
+        jmp -4
+        acc +6
+    ";
+
     #[test]
+    #[cfg_attr(no_puzzle_input_day8, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 1749);
@@ -116,4 +954,264 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_repairs() {
+        let program = Program::handheld(EX).unwrap();
+        let repairs = repairs(&program).unwrap();
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].pc, 7);
+        assert_eq!(program.display(&repairs[0].instruction), "nop -4");
+        assert_eq!(repairs[0].acc, 8);
+        assert_eq!(
+            super::repairs(&Program::handheld("acc +1").unwrap()),
+            Ok(vec![])
+        );
+        let extended = Program::assemble(InstructionSet::extended(), "hlt").unwrap();
+        assert!(super::repairs(&extended).is_err());
+
+        // every repair of random programs, compared against flipping each
+        // instruction and running the result
+        let mut rng = Rng::new(8);
+        for _ in 0..200 {
+            let source = (0..rng.range(1..=12))
+                .map(|_| {
+                    let op = ["nop", "acc", "jmp"][rng.below(3) as usize];
+                    format!("{} {:+}\n", op, rng.range(0..=8) as i64 - 4)
+                })
+                .collect::<String>();
+            let program = Program::handheld(&source).unwrap();
+            if Vm::new(&program).run() == State::Terminated {
+                continue;
+            }
+            let mut expected = Vec::new();
+            for (pc, line) in source.lines().enumerate() {
+                let flipped = match &line[..3] {
+                    "nop" => line.replace("nop", "jmp"),
+                    "jmp" => line.replace("jmp", "nop"),
+                    _ => continue,
+                };
+                let mut lines = source.lines().collect::<Vec<_>>();
+                lines[pc] = &flipped;
+                let repaired = Program::handheld(&lines.join("\n")).unwrap();
+                let mut vm = Vm::new(&repaired);
+                if vm.run() == State::Terminated {
+                    expected.push((pc, vm.registers()[0]));
+                }
+            }
+            let mut actual = super::repairs(&program)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.pc, r.acc))
+                .collect::<Vec<_>>();
+            actual.sort_unstable();
+            assert_eq!(actual, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_generated() {
+        for size in [1, 2, Solver::DEFAULT_SIZE, 100_000] {
+            let generated = Solver::generate(&mut Rng::new(size as u64), size);
+            let program = Program::handheld(&generated.input).unwrap();
+            assert_eq!(repairs(&program).unwrap().len(), 1);
+            let (res1, res2) = Solver::run_on(&generated.input);
+            assert_eq!(Some(res1.to_string()), generated.part1);
+            assert_eq!(Some(res2.to_string()), generated.part2);
+        }
+    }
+
+    #[test]
+    fn test_vm() {
+        let program = Program::handheld(EX).unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(vm.step(), State::Running);
+        assert_eq!((vm.pc(), vm.registers()), (1, &[0][..]));
+
+        vm.add_breakpoint(3);
+        assert_eq!(vm.run(), State::Breakpoint(3));
+        assert_eq!((vm.pc(), vm.registers()), (3, &[2][..]));
+        assert_eq!(vm.run(), State::Loop(vec![1, 2, 6, 7, 3, 4]));
+        assert_eq!(vm.registers(), [5]);
+        assert_eq!(
+            vm.trace().iter().map(|s| s.pc).collect::<Vec<_>>(),
+            vec![0, 1, 2, 6, 7, 3, 4]
+        );
+
+        let mut vm = Vm::new(&program);
+        assert_eq!(vm.run_until(|vm| vm.registers()[0] > 1), State::Running);
+        assert_eq!((vm.pc(), vm.registers()), (7, &[2][..]));
+
+        let program = Program::handheld("acc +1\njmp -2").unwrap();
+        assert_eq!(Vm::new(&program).run(), State::OutOfBounds(-1));
+    }
+
+    #[test]
+    fn test_assembler() {
+        let source = "
+            ; computes 5! into acc
+                    set a 5
+                    set b 1
+            loop:   mul b, a    # b *= a
+                    add a -1
+                    jnz a loop
+                    acc b
+            done:   hlt
+        ";
+        let program = Program::assemble(InstructionSet::extended(), source).unwrap();
+        assert_eq!(
+            program.to_string(),
+            "set a 5\nset b 1\nmul b a\nadd a -1\njnz a -2\nacc b\nhlt\n"
+        );
+        let mut vm = Vm::new(&program);
+        assert_eq!(vm.run(), State::Halted);
+        assert_eq!(vm.registers(), [120, 0, 120, 0, 0]);
+
+        let again = Program::assemble(InstructionSet::extended(), &program.to_string()).unwrap();
+        assert_eq!(again.code, program.code);
+        let handheld = Program::handheld(EX).unwrap();
+        assert_eq!(
+            Program::handheld(&handheld.to_string()).unwrap().code,
+            handheld.code
+        );
+
+        let errors = [
+            ("frob +1", "line 1: unknown instruction frob"),
+            ("nop", "line 1: nop takes 1 operands, got 0"),
+            ("jmp nowhere", "line 1: unknown label nowhere"),
+            ("a: nop +0\na: nop +0", "line 2: duplicate label a"),
+            (
+                "acc a",
+                "line 1: invalid operand a for acc, expected Immediate",
+            ),
+        ];
+        for (source, error) in errors.iter() {
+            assert_eq!(Program::handheld(source).err().as_deref(), Some(*error));
+        }
+    }
+
+    #[test]
+    fn test_registered_instruction() {
+        let mut isa = InstructionSet::handheld();
+        isa.register("dbl", &[], Control::Fixed, |cpu| {
+            *cpu.acc() *= 2;
+            Flow::Next
+        })
+        .unwrap();
+        assert!(isa
+            .register("acc", &[], Control::Fixed, |_| Flow::Next)
+            .is_err());
+        let program = Program::assemble(isa, "acc +3\ndbl\ndbl").unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(vm.run(), State::Terminated);
+        assert_eq!(vm.registers(), [12]);
+    }
+
+    #[test]
+    fn test_conditional_loop() {
+        let source = "
+            set a 3
+            top: jnz a +2
+            jmp top
+            add a -1
+            jmp top
+        ";
+        let program = Program::assemble(InstructionSet::extended(), source).unwrap();
+        assert_eq!(Vm::new(&program).run(), State::Loop(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let source = "
+            set a 2
+            top: mul a a
+            add b 9223372036854775807
+            acc b
+            jnz a top
+            hlt
+        ";
+        let program = Program::assemble(InstructionSet::extended(), source).unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(vm.run(), State::Halted);
+        assert_eq!(vm.registers(), &[i64::MAX - 20, 0, -6, 0, 0]);
+
+        let program = Program::handheld("nop +0\njmp +9223372036854775807").unwrap();
+        assert_eq!(Vm::new(&program).run(), State::OutOfBounds(i64::MAX));
+    }
+
+    #[test]
+    fn test_trace_limit() {
+        // Counts up forever, without ever repeating a state.
+        let source = "
+            top: add a 1
+            jnz a top
+        ";
+        let program = Program::assemble(InstructionSet::extended(), source).unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(
+            vm.run_until(|vm| vm.steps() >= 3 * TRACE_LIMIT),
+            State::Running
+        );
+        assert_eq!(vm.steps(), 3 * TRACE_LIMIT);
+        assert_eq!(vm.trace().len(), TRACE_LIMIT);
+        assert_eq!(
+            vm.trace().back().unwrap().registers[1],
+            3 * TRACE_LIMIT as i64 / 2
+        );
+
+        // Loops are still reported when their start is no longer traced.
+        let program = Program::handheld(EX).unwrap();
+        let mut vm = Vm::with_trace_limit(&program, 2);
+        assert_eq!(vm.run(), State::Loop(vec![1, 2, 6, 7, 3, 4]));
+        assert_eq!(vm.trace().len(), 2);
+
+        let source = "
+            set a 3
+            top: jnz a +2
+            jmp top
+            add a -1
+            jmp top
+        ";
+        let program = Program::assemble(InstructionSet::extended(), source).unwrap();
+        let mut vm = Vm::with_trace_limit(&program, 0);
+        assert_eq!(vm.run(), State::Loop(vec![1, 2]));
+        assert!(vm.trace().is_empty());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = Program::handheld(EX).unwrap();
+        let listing = disassemble(&program);
+        let mut lines = listing.lines();
+        assert_eq!(lines.next(), Some("0  nop +0"));
+        assert_eq!(lines.nth(1), Some("2  jmp +4   ; -> 6"));
+    }
+
+    #[test]
+    fn test_debug() {
+        let program = Program::handheld(EX).unwrap();
+        let mut out = Vec::new();
+        debug(&program, "b 6\nc\ns 2\nc\nq\ns\n".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let out = out.trim_start_matches(HELP);
+        assert_eq!(
+            out,
+            "breakpoint at 6
+pc=6 acc=1 steps=3: stopped at breakpoint 6
+pc=3 acc=2 steps=5: running
+pc=1 acc=5 steps=7: loop detected: 1 -> 2 -> 6 -> 7 -> 3 -> 4
+"
+        );
+
+        // Counts up without repeating a state, so continuing has to stop.
+        let program =
+            Program::assemble(InstructionSet::extended(), "top: add a 1\njnz a top").unwrap();
+        let mut out = Vec::new();
+        debug(&program, "c\n".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out.trim_start_matches(HELP),
+            "pc=0 acc=0 a=50000 b=0 c=0 d=0 steps=100000: running\n"
+        );
+    }
 }
@@ -1,46 +1,128 @@
+use aoc2020::StreamingSolution;
+use std::collections::{HashMap, VecDeque};
+
+pub(crate) const PREAMBLE: usize = 25;
+
 register!(
     "input/day9.txt";
     run(input: parse u64) -> u64 {
-        let part1 = run1(&input, 25);
-        (part1, run2(input, part1))
+        run_stream(input.into_iter(), PREAMBLE)
     }
 );
 
-fn run1(input: &[u64], pre_len: usize) -> u64 {
-    input
-        .windows(pre_len + 1)
-        .find_map(|win| {
-            let (&check, nums) = win.split_last().unwrap();
+// Keeps the last `preamble` numbers together with a multiset of the sums
+// of all pairs of different numbers among them, so that each new number
+// is checked with a single lookup and updates the sums in O(preamble).
+pub(crate) struct Validator {
+    preamble: usize,
+    window: VecDeque<u64>,
+    sums: HashMap<u64, usize>,
+}
 
-            if nums
-                .iter()
-                .copied()
-                .flat_map(|n| {
-                    nums.iter()
-                        .copied()
-                        .filter(move |&m| n != m)
-                        .map(move |m| n + m)
-                })
-                .find(|&sum| sum == check)
-                .is_none()
-            {
-                return Some(check);
+impl Validator {
+    pub(crate) fn new(preamble: usize) -> Self {
+        Self {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            sums: HashMap::new(),
+        }
+    }
+
+    // Returns whether the number is valid, numbers in the preamble always are.
+    pub(crate) fn push(&mut self, num: u64) -> bool {
+        let valid = self.window.len() < self.preamble || self.sums.contains_key(&num);
+
+        // a sum that does not fit cannot be the sum of any number
+        for &other in self.window.iter().filter(|&&other| other != num) {
+            if let Some(sum) = num.checked_add(other) {
+                *self.sums.entry(sum).or_default() += 1;
             }
+        }
+        self.window.push_back(num);
+
+        if self.window.len() > self.preamble {
+            let old = self.window.pop_front().unwrap();
+            for &other in self.window.iter().filter(|&&other| other != old) {
+                let sum = match old.checked_add(other) {
+                    Some(sum) => sum,
+                    None => continue,
+                };
+                match self.sums.get_mut(&sum) {
+                    Some(count) if *count > 1 => *count -= 1,
+                    _ => {
+                        let _ = self.sums.remove(&sum);
+                    }
+                }
+            }
+        }
+        valid
+    }
+}
 
-            None
-        })
-        .unwrap()
+// All numbers that are not the sum of two different numbers among the
+// `preamble` numbers before them, with their position in the input.
+pub(crate) fn invalid_numbers(
+    input: impl Iterator<Item = u64>,
+    preamble: usize,
+) -> impl Iterator<Item = (usize, u64)> {
+    let mut validator = Validator::new(preamble);
+    input
+        .enumerate()
+        .filter(move |&(_, num)| !validator.push(num))
+}
+
+impl StreamingSolution for Solver {
+    fn run_stream<I>(input: I) -> (u64, u64)
+    where
+        I: Iterator<Item = u64>,
+    {
+        run_stream(input, PREAMBLE)
+    }
 }
 
-fn run2(input: Vec<u64>, needle: u64) -> u64 {
-    for i in 2..input.len() {
-        if let Some(xs) = input.windows(i).find(|xs| xs.iter().sum::<u64>() == needle) {
-            let min = xs.iter().min().unwrap();
-            let max = xs.iter().max().unwrap();
-            return min + max;
+pub(crate) fn run_stream(mut input: impl Iterator<Item = u64>, preamble: usize) -> (u64, u64) {
+    // The range for part 2 may lie anywhere before the invalid number,
+    // so that prefix has to be kept. Everything after it is only held
+    // as long as it is part of the current window.
+    let mut validator = Validator::new(preamble);
+    let mut prefix = Vec::new();
+    let part1 = loop {
+        let num = input.next().expect("no solution");
+        if !validator.push(num) {
+            break num;
+        }
+        prefix.push(num);
+    };
+
+    let rest = prefix.into_iter().chain(Some(part1)).chain(input);
+    let (_, range) = find_range(rest, part1).expect("no solution");
+    let min = range.iter().min().unwrap();
+    let max = range.iter().max().unwrap();
+    (part1, min + max)
+}
+
+// Finds the first run of at least two numbers that sum up to `needle`,
+// returning its start position and the numbers.
+pub(crate) fn find_range(
+    input: impl Iterator<Item = u64>,
+    needle: u64,
+) -> Option<(usize, Vec<u64>)> {
+    let mut window = VecDeque::new();
+    let mut start = 0;
+    let mut sum = 0_u64;
+    for num in input {
+        // a sum that does not fit is certainly too large
+        while !window.is_empty() && !matches!(sum.checked_add(num), Some(next) if next <= needle) {
+            sum -= window.pop_front().unwrap();
+            start += 1;
+        }
+        window.push_back(num);
+        sum += num;
+        if sum == needle && window.len() > 1 {
+            return Some((start, window.into()));
         }
     }
-    panic!("no solution")
+    None
 }
 
 #[cfg(test)]
@@ -49,12 +131,21 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day9, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 1309761972);
         assert_eq!(res2, 177989832);
     }
 
+    #[test]
+    #[cfg_attr(no_puzzle_input_day9, ignore = "puzzle input not available")]
+    fn test_stream() {
+        let (res1, res2) = Solver::run_reader(Solver::puzzle_input().as_bytes());
+        assert_eq!(res1, 1309761972);
+        assert_eq!(res2, 177989832);
+    }
+
     fn input() -> Vec<u64> {
         Solver::parse_input(
             "
@@ -84,7 +175,56 @@ mod tests {
 
     #[test]
     fn test_ex1() {
-        assert_eq!(127, run1(&input(), 5));
-        assert_eq!(62, run2(input(), 127));
+        assert_eq!((127, 62), run_stream(input().into_iter(), 5));
+        assert_eq!(
+            find_range(input().into_iter(), 127),
+            Some((2, vec![15, 25, 47, 40]))
+        );
+        assert_eq!(find_range(input().into_iter(), 1), None);
+
+        let max = u64::MAX;
+        let nums = vec![max - 1, 5, max, 2, 3];
+        assert_eq!(find_range(nums.into_iter(), 5), Some((3, vec![2, 3])));
+        assert_eq!(find_range(vec![1, max].into_iter(), max), None);
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        assert_eq!(
+            invalid_numbers(input().into_iter(), 5).collect::<Vec<_>>(),
+            vec![(14, 127)]
+        );
+        for preamble in 1..=6 {
+            let expected = input()
+                .iter()
+                .enumerate()
+                .skip(preamble)
+                .filter(|&(i, &num)| {
+                    let window = &input()[i - preamble..i];
+                    !window
+                        .iter()
+                        .any(|&a| window.iter().any(|&b| a != b && a + b == num))
+                })
+                .map(|(i, &num)| (i, num))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                invalid_numbers(input().into_iter(), preamble).collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        let max = u64::MAX;
+        let nums = vec![max, max - 1, 1, max, 2];
+        assert_eq!(
+            invalid_numbers(nums.into_iter(), 2).collect::<Vec<_>>(),
+            vec![(2, 1), (4, 2)]
+        );
+
+        // sums of a number with itself do not count, but duplicates do
+        let nums = vec![1, 1, 2, 3, 6, 2, 2, 4];
+        assert_eq!(
+            invalid_numbers(nums.into_iter(), 2).collect::<Vec<_>>(),
+            vec![(2, 2), (4, 6), (5, 2), (6, 2), (7, 4)]
+        );
     }
 }
@@ -1,4 +1,5 @@
-use std::mem;
+use aoc2020::{Generated, Generator, Rng, StreamingSolution};
+use std::{fmt, ops::AddAssign};
 
 register!(
     "input/day10.txt";
@@ -7,12 +8,62 @@ register!(
     }
 );
 
-fn run(mut input: Vec<u64>) -> (u64, u64) {
-    input.sort_unstable();
-    input.insert(0, 0);
-    input.push(3 + *input.iter().max().unwrap());
+pub(crate) const MAX_STEP: u64 = 3;
 
-    (run1(&input), run2(input))
+fn run(input: Vec<u64>) -> (u64, u64) {
+    let chain = Chain::new(input, MAX_STEP);
+    let arrangements = chain
+        .arrangements()
+        .to_u64()
+        .expect("Too many arrangements for a u64");
+    (run1(&chain.jolts), arrangements)
+}
+
+impl StreamingSolution for Solver {
+    fn run_stream<I>(input: I) -> (u64, u64)
+    where
+        I: Iterator<Item = u64>,
+    {
+        // The adapters have to be sorted, so all of them are kept,
+        // but only as numbers and not as the full input text.
+        run(input.collect())
+    }
+}
+
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 100;
+
+    // The chain is built from runs of up to four 1-jolt gaps, separated by
+    // 3-jolt gaps. Every run of length n contributes a known factor to the
+    // number of arrangements.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        let mut adapters = Vec::with_capacity(size);
+        let mut jolts = 0;
+        let (mut diff1, mut diff3) = (0_u64, 1_u64);
+        let mut arrangements = Some(1_u64);
+        while adapters.len() < size {
+            if jolts > 0 {
+                jolts += 3;
+                diff3 += 1;
+                adapters.push(jolts);
+            }
+            let run = (rng.range(1..=4) as usize).min(size - adapters.len());
+            for _ in 0..run {
+                jolts += 1;
+                adapters.push(jolts);
+            }
+            diff1 += run as u64;
+            let factor = [1, 1, 2, 4, 7][run];
+            arrangements = arrangements.and_then(|a| a.checked_mul(factor));
+        }
+        rng.shuffle(&mut adapters);
+
+        Generated {
+            input: adapters.iter().map(|a| format!("{}\n", a)).collect(),
+            part1: Some((diff1 * diff3).to_string()),
+            part2: arrangements.map(|a| a.to_string()),
+        }
+    }
 }
 
 fn run1(input: &[u64]) -> u64 {
@@ -26,26 +77,200 @@ fn run1(input: &[u64]) -> u64 {
     diff1 * diff3
 }
 
-fn run2(input: Vec<u64>) -> u64 {
-    input
-        .windows(2)
-        .map(|w| w[1] - w[0])
-        .scan(0, |run, diff| {
-            Some(match diff {
-                1 => {
-                    *run += 1;
-                    None
+// The outlet, all adapters in ascending order and the device, which is
+// rated `max_step` higher than the highest adapter.
+pub(crate) struct Chain {
+    jolts: Vec<u64>,
+    max_step: u64,
+}
+
+impl Chain {
+    pub(crate) fn new(mut adapters: Vec<u64>, max_step: u64) -> Self {
+        adapters.sort_unstable();
+        let device = adapters.last().copied().unwrap_or_default() + max_step;
+        adapters.insert(0, 0);
+        adapters.push(device);
+        Self {
+            jolts: adapters,
+            max_step,
+        }
+    }
+
+    fn fits(&self, from: usize, to: usize) -> bool {
+        (1..=self.max_step).contains(&(self.jolts[to] - self.jolts[from]))
+    }
+
+    // The adapters that can be plugged into the one at `from`.
+    fn next(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        (from + 1..self.jolts.len())
+            .take_while(move |&to| self.jolts[to] - self.jolts[from] <= self.max_step)
+            .filter(move |&to| self.fits(from, to))
+    }
+
+    // Number of ways to get from each joltage to the device, filled from
+    // the back. Works for any gaps, a gap that is too large makes
+    // everything before it unreachable.
+    fn ways_to_device(&self) -> Vec<Count> {
+        let mut ways = vec![Count::from(0); self.jolts.len()];
+        if let Some(last) = ways.last_mut() {
+            *last = Count::from(1);
+        }
+        for from in (0..self.jolts.len() - 1).rev() {
+            let mut total = Count::from(0);
+            for to in self.next(from) {
+                total += &ways[to];
+            }
+            ways[from] = total;
+        }
+        ways
+    }
+
+    pub(crate) fn arrangements(&self) -> Count {
+        self.ways_to_device().swap_remove(0)
+    }
+
+    // Lists the arrangements in lexicographic order, each one as the adapters
+    // that are used, without the outlet and the device.
+    pub(crate) fn enumerate(&self) -> Arrangements<'_> {
+        let viable = self.ways_to_device().iter().map(|w| !w.is_zero()).collect();
+        Arrangements {
+            chain: self,
+            viable,
+            path: Vec::new(),
+            started: false,
+        }
+    }
+}
+
+pub(crate) struct Arrangements<'a> {
+    chain: &'a Chain,
+    viable: Vec<bool>,
+    path: Vec<usize>,
+    started: bool,
+}
+
+impl Arrangements<'_> {
+    fn next_viable(&self, from: usize, after: usize) -> Option<usize> {
+        self.chain
+            .next(from)
+            .find(|&to| to > after && self.viable[to])
+    }
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let device = self.chain.jolts.len() - 1;
+        if !self.started {
+            self.started = true;
+            if !self.viable[0] {
+                return None;
+            }
+            self.path.push(0);
+        } else {
+            // Backtrack to the last adapter that has an unused alternative.
+            loop {
+                let previous = self.path.pop()?;
+                let &from = self.path.last()?;
+                if let Some(to) = self.next_viable(from, previous) {
+                    self.path.push(to);
+                    break;
                 }
-                _ => match mem::take(run) {
-                    2 => Some(2),
-                    3 => Some(4),
-                    4 => Some(7),
-                    _ => None,
-                },
-            })
-        })
-        .filter_map(|r| r)
-        .product()
+            }
+        }
+
+        // Only viable adapters are on the path, so the device is always reached.
+        while let Some(&from) = self.path.last().filter(|&&from| from != device) {
+            let to = self.next_viable(from, from)?;
+            self.path.push(to);
+        }
+
+        Some(
+            self.path[1..self.path.len() - 1]
+                .iter()
+                .map(|&i| self.chain.jolts[i])
+                .collect(),
+        )
+    }
+}
+
+// A minimal unsigned big integer, just enough to add up arrangements.
+// Little endian base 2^32 digits, without trailing zeros.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Count(Vec<u32>);
+
+impl Count {
+    pub(crate) fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn to_u64(&self) -> Option<u64> {
+        match self.0[..] {
+            [] => Some(0),
+            [lo] => Some(u64::from(lo)),
+            [lo, hi] => Some(u64::from(hi) << 32 | u64::from(lo)),
+            _ => None,
+        }
+    }
+
+    // Divides in place and returns the remainder.
+    fn div_rem(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0_u64;
+        for digit in self.0.iter_mut().rev() {
+            let cur = rem << 32 | u64::from(*digit);
+            *digit = (cur / u64::from(divisor)) as u32;
+            rem = cur % u64::from(divisor);
+        }
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        rem as u32
+    }
+}
+
+impl From<u64> for Count {
+    fn from(n: u64) -> Self {
+        let mut count = Self(vec![n as u32, (n >> 32) as u32]);
+        while count.0.last() == Some(&0) {
+            count.0.pop();
+        }
+        count
+    }
+}
+
+impl AddAssign<&Count> for Count {
+    fn add_assign(&mut self, rhs: &Count) {
+        if self.0.len() < rhs.0.len() {
+            self.0.resize(rhs.0.len(), 0);
+        }
+        let mut carry = 0_u64;
+        for (i, digit) in self.0.iter_mut().enumerate() {
+            let sum = u64::from(*digit) + u64::from(rhs.0.get(i).copied().unwrap_or(0)) + carry;
+            *digit = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u32 = 1_000_000_000;
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        loop {
+            chunks.push(n.div_rem(CHUNK));
+            if n.is_zero() {
+                break;
+            }
+        }
+        let mut chunks = chunks.into_iter().rev();
+        write!(f, "{}", chunks.next().unwrap_or_default())?;
+        chunks.try_for_each(|chunk| write!(f, "{:09}", chunk))
+    }
 }
 
 #[cfg(test)]
@@ -54,12 +279,29 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day10, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 2738);
         assert_eq!(res2, 74049191673856);
     }
 
+    #[test]
+    #[cfg_attr(no_puzzle_input_day10, ignore = "puzzle input not available")]
+    fn test_stream() {
+        let (res1, res2) = Solver::run_reader(Solver::puzzle_input().as_bytes());
+        assert_eq!(res1, 2738);
+        assert_eq!(res2, 74049191673856);
+    }
+
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
     #[test]
     fn test_ex1() {
         assert_eq!(
@@ -101,4 +343,100 @@ mod tests {
             )
         );
     }
+
+    // Tries every subset of the adapters.
+    fn brute_force(adapters: &[u64], max_step: u64) -> Vec<Vec<u64>> {
+        let mut adapters = adapters.to_vec();
+        adapters.sort_unstable();
+        let device = adapters.last().copied().unwrap_or_default() + max_step;
+        let mut found = (0..1_u32 << adapters.len())
+            .map(|mask| {
+                (0..adapters.len())
+                    .filter(|i| mask & 1 << i != 0)
+                    .map(|i| adapters[i])
+                    .collect::<Vec<_>>()
+            })
+            .filter(|used| {
+                let mut jolts = vec![0];
+                jolts.extend(used);
+                jolts.push(device);
+                jolts
+                    .windows(2)
+                    .all(|w| (1..=max_step).contains(&(w[1] - w[0])))
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_any_gaps() {
+        let mut rng = Rng::new(10);
+        for max_step in 1..=4 {
+            for _ in 0..50 {
+                let mut jolts = 0;
+                let adapters = (0..rng.range(0..=12))
+                    .map(|_| {
+                        jolts += rng.range(1..=max_step + 1);
+                        jolts
+                    })
+                    .collect::<Vec<_>>();
+                let expected = brute_force(&adapters, max_step);
+                let chain = Chain::new(adapters, max_step);
+                assert_eq!(chain.arrangements(), Count::from(expected.len() as u64));
+                assert_eq!(chain.enumerate().collect::<Vec<_>>(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_runs() {
+        // A run of n 1-jolt gaps has tribonacci many arrangements.
+        let mut tribonacci = vec![1_u128, 1, 2];
+        while tribonacci.len() <= 120 {
+            let n = tribonacci.len();
+            tribonacci.push(tribonacci[n - 1] + tribonacci[n - 2] + tribonacci[n - 3]);
+        }
+        for n in [5, 10, 64, 120] {
+            let chain = Chain::new((1..=n as u64).collect(), 3);
+            assert_eq!(chain.arrangements().to_string(), tribonacci[n].to_string());
+        }
+        assert_eq!(
+            Chain::new(vec![1, 2, 3, 4, 5], 3).arrangements(),
+            Count::from(13)
+        );
+        assert_eq!(
+            Chain::new((1..=120).collect(), 3).arrangements().to_u64(),
+            None
+        );
+        assert_eq!(
+            Chain::new(vec![1, 2, 4, 5, 7], 3).arrangements(),
+            Count::from(8)
+        );
+        assert_eq!(
+            Chain::new(vec![1, 2, 3, 4, 5], 5).arrangements(),
+            Count::from(16)
+        );
+        assert!(Chain::new(vec![1, 5, 6], 3).arrangements().is_zero());
+        assert_eq!(Chain::new(vec![1, 5, 6], 3).enumerate().next(), None);
+    }
+
+    #[test]
+    fn test_count() {
+        let mut count = Count::from(u64::MAX);
+        count += &Count::from(1);
+        assert_eq!(count.to_string(), "18446744073709551616");
+        assert_eq!(count.to_u64(), None);
+        assert_eq!(Count::from(0).to_string(), "0");
+        assert_eq!(Count::from(1_000_000_007).to_string(), "1000000007");
+        assert_eq!(Count::from(u64::MAX).to_u64(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let chain = Chain::new(vec![1, 2, 3], 3);
+        let all = chain.enumerate().collect::<Vec<_>>();
+        assert_eq!(all, vec![vec![1, 2, 3], vec![1, 3], vec![2, 3], vec![3]]);
+        assert_eq!(chain.enumerate().take(2).count(), 2);
+    }
 }
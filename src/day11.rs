@@ -1,72 +1,366 @@
-use std::iter::successors;
+use std::{collections::HashMap, fmt, mem, str::FromStr};
 
 register!(
     "input/day11.txt";
     run(input: Vec<u8>) -> usize {
         (
-            run_any(input.clone(), 1, 4),
-            run_any(input, usize::max_value(), 5),
+            run_any(&input, &Rules::PART1),
+            run_any(&input, &Rules::PART2),
         )
     }
 );
 
-fn run_any(mut input: Vec<Vec<u8>>, dist: usize, full: usize) -> usize {
-    loop {
-        let new_input = input
-            .iter()
-            .enumerate()
-            .map(|(y, row)| {
-                row.iter()
-                    .enumerate()
-                    .map(|(x, col)| match col {
-                        b'L' if occupied_seats(x, y, dist, &input) == 0 => b'#',
-                        b'#' if occupied_seats(x, y, dist, &input) >= full => b'L',
-                        otherwise => *otherwise,
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        if new_input == input {
-            break new_input
-                .into_iter()
-                .flatten()
-                .filter(|&c| c == b'#')
-                .count();
+fn run_any(input: &[Vec<u8>], rules: &Rules) -> usize {
+    let layout = Layout::new(input, rules).unwrap_or_else(|e| panic!("{}", e));
+    simulate(&layout, rules).occupied
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Cell {
+    Floor,
+    Empty,
+    Occupied,
+    // A seat that must stay empty, it still blocks the view.
+    Blocked,
+}
+
+impl Cell {
+    fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            b'.' => Some(Self::Floor),
+            b'L' => Some(Self::Empty),
+            b'#' => Some(Self::Occupied),
+            b'X' => Some(Self::Blocked),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Floor => b'.',
+            Self::Empty => b'L',
+            Self::Occupied => b'#',
+            Self::Blocked => b'X',
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Neighbourhood {
+    // All eight directions.
+    Moore,
+    // Only up, down, left and right.
+    VonNeumann,
+}
+
+impl Neighbourhood {
+    fn directions(self) -> &'static [(isize, isize)] {
+        match self {
+            Self::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Self::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+        }
+    }
+}
+
+// An empty seat gets occupied if at most `occupy` of the visible seats are
+// occupied, an occupied seat is vacated if at least `vacate` of them are.
+// Seats further away than `sight` steps are not visible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Rules {
+    pub(crate) sight: usize,
+    pub(crate) neighbourhood: Neighbourhood,
+    pub(crate) vacate: usize,
+    pub(crate) occupy: usize,
+}
+
+impl Rules {
+    pub(crate) const PART1: Self = Self {
+        sight: 1,
+        neighbourhood: Neighbourhood::Moore,
+        vacate: 4,
+        occupy: 0,
+    };
+
+    pub(crate) const PART2: Self = Self {
+        sight: usize::MAX,
+        neighbourhood: Neighbourhood::Moore,
+        vacate: 5,
+        occupy: 0,
+    };
+}
+
+// Parses `key=value` pairs, e.g. `sight=any,shape=vonneumann,vacate=3`.
+// Keys that are not given keep the rules of part 1.
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |key: &str, value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("invalid {} {}", key, value))
+        };
+        let mut rules = Self::PART1;
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value but got {}", part))?;
+            match key.trim() {
+                "sight" if value == "any" => rules.sight = usize::MAX,
+                "sight" => rules.sight = number(key, value)?,
+                "shape" => {
+                    rules.neighbourhood = match value {
+                        "moore" => Neighbourhood::Moore,
+                        "vonneumann" => Neighbourhood::VonNeumann,
+                        _ => return Err(format!("unknown shape {}", value)),
+                    }
+                }
+                "vacate" => rules.vacate = number(key, value)?,
+                "occupy" => rules.occupy = number(key, value)?,
+                _ => return Err(format!("unknown rule {}", key)),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sight {
+            usize::MAX => f.write_str("sight=any")?,
+            sight => write!(f, "sight={}", sight)?,
         }
-        input = new_input;
-    }
-}
-
-fn occupied_seats(x: usize, y: usize, limit: usize, rows: &[Vec<u8>]) -> usize {
-    (-1..=1)
-        .flat_map(|dx| {
-            (-1..=1)
-                .filter(move |&dy| dx != 0 || dy != 0)
-                .map(move |dy| {
-                    go(x, y, dx, dy, rows[y].len(), rows.len())
-                        .take(limit)
-                        .map(|(x, y)| rows[y][x])
-                        .take_while(|&s| s != b'L')
-                        .any(|s| s == b'#') as usize
-                })
+        let shape = match self.neighbourhood {
+            Neighbourhood::Moore => "moore",
+            Neighbourhood::VonNeumann => "vonneumann",
+        };
+        write!(
+            f,
+            ",shape={},vacate={},occupy={}",
+            shape, self.vacate, self.occupy
+        )
+    }
+}
+
+// The seats and, for every seat, the first seat that is visible in each
+// direction of the neighbourhood. Floor and blocked seats are not stored as
+// seats, the neighbours of all seats are kept in one flat list.
+pub(crate) struct Layout {
+    width: usize,
+    grid: Vec<Cell>,
+    cells: Vec<usize>,
+    initial: Vec<bool>,
+    offsets: Vec<usize>,
+    neighbours: Vec<u32>,
+}
+
+impl Layout {
+    pub(crate) fn new(rows: &[Vec<u8>], rules: &Rules) -> Result<Self, String> {
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        let mut grid = vec![Cell::Floor; width * rows.len()];
+        let mut index = vec![None; width * rows.len()];
+        let mut cells = Vec::new();
+        let mut initial = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &b) in row.iter().enumerate() {
+                let cell = Cell::from_byte(b).ok_or_else(|| {
+                    format!("unknown cell {} at {},{}", char::from(b), x + 1, y + 1)
+                })?;
+                grid[y * width + x] = cell;
+                if let Cell::Empty | Cell::Occupied = cell {
+                    index[y * width + x] = Some(cells.len() as u32);
+                    cells.push(y * width + x);
+                    initial.push(cell == Cell::Occupied);
+                }
+            }
+        }
+
+        let height = rows.len() as isize;
+        let mut offsets = vec![0];
+        let mut neighbours = Vec::new();
+        for &cell in &cells {
+            let (x, y) = ((cell % width) as isize, (cell / width) as isize);
+            for &(dx, dy) in rules.neighbourhood.directions() {
+                let (mut nx, mut ny) = (x, y);
+                for _ in 0..rules.sight {
+                    nx += dx;
+                    ny += dy;
+                    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height {
+                        break;
+                    }
+                    let at = ny as usize * width + nx as usize;
+                    if let Some(seat) = index[at] {
+                        neighbours.push(seat);
+                    }
+                    if grid[at] != Cell::Floor {
+                        break;
+                    }
+                }
+            }
+            offsets.push(neighbours.len());
+        }
+
+        Ok(Self {
+            width,
+            grid,
+            cells,
+            initial,
+            offsets,
+            neighbours,
         })
-        .sum()
+    }
+
+    pub(crate) fn seats(&self) -> usize {
+        self.cells.len()
+    }
+
+    // Draws the grid with `#` for the occupied seats in `state`.
+    fn render(&self, state: &[bool]) -> String {
+        let mut grid = self.grid.iter().map(|c| c.to_byte()).collect::<Vec<_>>();
+        for (&cell, &occupied) in self.cells.iter().zip(state) {
+            grid[cell] = if occupied { b'#' } else { b'L' };
+        }
+        grid.chunks(self.width.max(1))
+            .map(|row| format!("{}\n", String::from_utf8_lossy(row)))
+            .collect()
+    }
+
+    fn neighbours(&self, seat: usize) -> impl Iterator<Item = usize> + '_ {
+        self.neighbours[self.offsets[seat]..self.offsets[seat + 1]]
+            .iter()
+            .map(|&n| n as usize)
+    }
 }
 
-fn go(
-    x: usize,
-    y: usize,
-    dx: isize,
-    dy: isize,
-    mx: usize,
-    my: usize,
-) -> impl Iterator<Item = (usize, usize)> {
-    successors(Some((x as isize, y as isize)), move |&(x, y)| {
-        Some((x + dx, y + dy))
-            .filter(|&(x, y)| x >= 0 && x < (mx as isize) && y >= 0 && y < (my as isize))
-    })
-    .skip(1)
-    .map(|(x, y)| (x as usize, y as usize))
+// Runs rounds on two buffers, only the seats next to a seat that changed in
+// the previous round can change in the next one, so only those are checked.
+pub(crate) struct Simulation<'a> {
+    layout: &'a Layout,
+    rules: Rules,
+    current: Vec<bool>,
+    next: Vec<bool>,
+    candidates: Vec<usize>,
+    queued: Vec<bool>,
+    changed: Vec<usize>,
+}
+
+impl<'a> Simulation<'a> {
+    pub(crate) fn new(layout: &'a Layout, rules: &Rules) -> Self {
+        Self {
+            layout,
+            rules: *rules,
+            current: layout.initial.clone(),
+            next: layout.initial.clone(),
+            candidates: (0..layout.seats()).collect(),
+            queued: vec![true; layout.seats()],
+            changed: Vec::new(),
+        }
+    }
+
+    fn occupied_neighbours(&self, seat: usize) -> usize {
+        self.layout
+            .neighbours(seat)
+            .filter(|&n| self.current[n])
+            .count()
+    }
+
+    // Runs one round and returns how many seats changed.
+    pub(crate) fn step(&mut self) -> usize {
+        self.changed.clear();
+        for &seat in &self.candidates {
+            self.queued[seat] = false;
+            let occupied = self.occupied_neighbours(seat);
+            let new = match self.current[seat] {
+                false => occupied <= self.rules.occupy,
+                true => occupied < self.rules.vacate,
+            };
+            if new != self.current[seat] {
+                self.next[seat] = new;
+                self.changed.push(seat);
+            }
+        }
+        self.candidates.clear();
+        mem::swap(&mut self.current, &mut self.next);
+
+        for &seat in &self.changed {
+            self.next[seat] = self.current[seat];
+            for n in self.layout.neighbours(seat).chain(Some(seat)) {
+                if !self.queued[n] {
+                    self.queued[n] = true;
+                    self.candidates.push(n);
+                }
+            }
+        }
+        self.changed.len()
+    }
+
+    pub(crate) fn occupied(&self) -> usize {
+        self.current.iter().filter(|&&o| o).count()
+    }
+
+    pub(crate) fn render(&self) -> String {
+        self.layout.render(&self.current)
+    }
+
+    fn packed(&self) -> Vec<u64> {
+        self.current
+            .chunks(64)
+            .map(|c| c.iter().rev().fold(0, |bits, &o| bits << 1 | u64::from(o)))
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum End {
+    Stable,
+    // The seating after round `start` repeats every `period` rounds.
+    Oscillating { start: usize, period: usize },
+}
+
+pub(crate) struct Stats {
+    pub(crate) occupied: usize,
+    // Seats that changed in each round, the final round without changes
+    // is not included.
+    pub(crate) changes: Vec<usize>,
+    pub(crate) end: End,
+}
+
+pub(crate) fn simulate(layout: &Layout, rules: &Rules) -> Stats {
+    let mut simulation = Simulation::new(layout, rules);
+    run_to_end(&mut simulation)
+}
+
+// Runs until nothing changes or a seating repeats. For an oscillation,
+// `changes` stops at the first repeated round.
+pub(crate) fn run_to_end(simulation: &mut Simulation<'_>) -> Stats {
+    let mut seen = HashMap::new();
+    let mut changes = Vec::new();
+    let end = loop {
+        if let Some(start) = seen.insert(simulation.packed(), changes.len()) {
+            break End::Oscillating {
+                start,
+                period: changes.len() - start,
+            };
+        }
+        match simulation.step() {
+            0 => break End::Stable,
+            changed => changes.push(changed),
+        }
+    };
+    Stats {
+        occupied: simulation.occupied(),
+        changes,
+        end,
+    }
 }
 
 #[cfg(test)]
@@ -74,7 +368,13 @@ mod tests {
     use super::*;
     use aoc2020::Solution;
 
+    fn occupied_seats(x: usize, y: usize, layout: &Layout) -> usize {
+        let seat = layout.cells.binary_search(&(y * layout.width + x)).unwrap();
+        Simulation::new(layout, &Rules::PART2).occupied_neighbours(seat)
+    }
+
     #[test]
+    #[cfg_attr(no_puzzle_input_day11, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 2093);
@@ -102,6 +402,95 @@ mod tests {
         );
     }
 
+    const EX: &str = "
+        L.LL.LL.LL
+        LLLLLLL.LL
+        L.L.L..L..
+        LLLL.LL.LL
+        L.LL.LL.LL
+        L.LLLLL.LL
+        ..L.L.....
+        LLLLLLLLLL
+        L.LLLLLL.L
+        L.LLLLL.LL
+    ";
+
+    #[test]
+    fn test_stats() {
+        let input = Solver::parse_input(EX);
+        let layout = Layout::new(&input, &Rules::PART1).unwrap();
+        let stats = simulate(&layout, &Rules::PART1);
+        assert_eq!(stats.occupied, 37);
+        assert_eq!(stats.changes.len(), 5);
+        assert_eq!(stats.changes[0], layout.seats());
+
+        let mut simulation = Simulation::new(&layout, &Rules::PART1);
+        simulation.step();
+        simulation.step();
+        assert_eq!(
+            simulation.render().lines().take(2).collect::<Vec<_>>(),
+            vec!["#.LL.L#.##", "#LLLLLL.L#"]
+        );
+
+        let stats = simulate(&Layout::new(&input, &Rules::PART2).unwrap(), &Rules::PART2);
+        assert_eq!(stats.occupied, 26);
+        assert_eq!(stats.changes.len(), 6);
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!("".parse(), Ok(Rules::PART1));
+        assert_eq!("sight=any, vacate=5".parse(), Ok(Rules::PART2));
+        for rules in &[Rules::PART1, Rules::PART2] {
+            assert_eq!(rules.to_string().parse(), Ok(*rules));
+        }
+        let rules = "shape=vonneumann,occupy=1".parse::<Rules>().unwrap();
+        assert_eq!(rules.neighbourhood, Neighbourhood::VonNeumann);
+        assert_eq!(rules.occupy, 1);
+        assert!("sight=far".parse::<Rules>().is_err());
+        assert!("shape=hex".parse::<Rules>().is_err());
+        assert!("vacate".parse::<Rules>().is_err());
+        assert!("patience=3".parse::<Rules>().is_err());
+    }
+
+    #[test]
+    fn test_blocked() {
+        let input = Solver::parse_input("L.X.L\nLLLLL");
+        let layout = Layout::new(&input, &Rules::PART2).unwrap();
+        assert_eq!(layout.seats(), 7);
+        assert_eq!(occupied_seats(0, 0, &layout), 0);
+        // The blocked seat hides the seat behind it.
+        assert_eq!(layout.neighbours(0).count(), 2);
+
+        let mut simulation = Simulation::new(&layout, &Rules::PART1);
+        simulation.step();
+        assert_eq!(simulation.render(), "#.X.#\n#####\n");
+        assert!(Layout::new(&Solver::parse_input("L?L"), &Rules::PART1).is_err());
+    }
+
+    #[test]
+    fn test_oscillation() {
+        let rules = "vacate=1".parse().unwrap();
+        let input = Solver::parse_input("LL");
+        let stats = simulate(&Layout::new(&input, &rules).unwrap(), &rules);
+        assert_eq!(
+            stats.end,
+            End::Oscillating {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(stats.changes, vec![2, 2]);
+
+        let input = Solver::parse_input(EX);
+        let stats = simulate(&Layout::new(&input, &rules).unwrap(), &rules);
+        assert!(matches!(stats.end, End::Oscillating { .. }));
+        assert_eq!(
+            simulate(&Layout::new(&input, &Rules::PART1).unwrap(), &Rules::PART1).end,
+            End::Stable
+        );
+    }
+
     #[test]
     fn occupied_test1() {
         let input = "
@@ -119,8 +508,9 @@ mod tests {
         .lines()
         .map(|s| s.trim().as_bytes().to_vec())
         .collect::<Vec<_>>();
+        let layout = Layout::new(&input, &Rules::PART2).unwrap();
 
-        assert_eq!(8, occupied_seats(3, 4, usize::max_value(), &input));
+        assert_eq!(8, occupied_seats(3, 4, &layout));
     }
 
     #[test]
@@ -134,8 +524,9 @@ mod tests {
         .lines()
         .map(|s| s.trim().as_bytes().to_vec())
         .collect::<Vec<_>>();
+        let layout = Layout::new(&input, &Rules::PART2).unwrap();
 
-        assert_eq!(0, occupied_seats(1, 1, usize::max_value(), &input));
+        assert_eq!(0, occupied_seats(1, 1, &layout));
     }
 
     #[test]
@@ -153,8 +544,9 @@ mod tests {
         .lines()
         .map(|s| s.trim().as_bytes().to_vec())
         .collect::<Vec<_>>();
+        let layout = Layout::new(&input, &Rules::PART2).unwrap();
 
-        assert_eq!(0, occupied_seats(3, 3, usize::max_value(), &input));
+        assert_eq!(0, occupied_seats(3, 3, &layout));
     }
 
     #[test]
@@ -175,7 +567,8 @@ mod tests {
         .lines()
         .map(|s| s.trim().as_bytes().to_vec())
         .collect::<Vec<_>>();
+        let layout = Layout::new(&input, &Rules::PART2).unwrap();
 
-        assert_eq!(0, occupied_seats(3, 0, usize::max_value(), &input));
+        assert_eq!(0, occupied_seats(3, 0, &layout));
     }
 }
@@ -1,176 +1,469 @@
-use std::ops::{AddAssign, SubAssign};
+use std::{convert::TryFrom, fmt, str::FromStr};
 
 type Input = Dir;
-type Pos = (isize, isize);
+pub(crate) type Pos = (isize, isize);
+pub(crate) type Point = (f64, f64);
 
 register!(
     "input/day12.txt";
-    run(input: Input) -> usize {
+    run(input: parse Input) -> usize {
         (
-            Ship1::run(input.iter().copied()),
-            Ship2::run(input)
+            manhattan::<Ship1<Pos>>(&input),
+            manhattan::<Ship2<Pos>>(&input),
         )
     }
 );
 
-trait Ship: Default + Into<Pos> {
-    fn apply(&mut self, dir: Dir);
+fn manhattan<S: Ship<Vector = Pos>>(input: &[Input]) -> usize {
+    let (x, y) = S::run(input.iter().copied())
+        .unwrap_or_else(|e| panic!("{}", e))
+        .position();
+    x.unsigned_abs() + y.unsigned_abs()
+}
+
+// A position or direction on the grid, north is towards negative y.
+pub(crate) trait Vector: Copy + Default {
+    fn new(x: isize, y: isize) -> Self;
+
+    fn add_scaled(&mut self, other: Self, factor: isize) -> Result<(), String>;
+
+    // Turns clockwise, negative angles turn counter-clockwise.
+    fn rotate(&mut self, degrees: isize) -> Result<(), String>;
+
+    fn to_point(self) -> Point;
+}
+
+// Exact, but only for quarter turns.
+impl Vector for Pos {
+    fn new(x: isize, y: isize) -> Self {
+        (x, y)
+    }
+
+    fn add_scaled(&mut self, (x, y): Self, factor: isize) -> Result<(), String> {
+        let add = |a: isize, b: isize| b.checked_mul(factor).and_then(|b| a.checked_add(b));
+        *self = add(self.0, x).zip(add(self.1, y)).ok_or_else(overflow)?;
+        Ok(())
+    }
+
+    fn rotate(&mut self, degrees: isize) -> Result<(), String> {
+        if degrees % 90 != 0 {
+            return Err(format!(
+                "integer positions can only turn by multiples of 90 degrees, not {}",
+                degrees
+            ));
+        }
+        for _ in 0..(degrees / 90).rem_euclid(4) {
+            let (x, y) = *self;
+            *self = (y.checked_neg().ok_or_else(overflow)?, x);
+        }
+        Ok(())
+    }
+
+    fn to_point(self) -> Point {
+        (self.0 as f64, self.1 as f64)
+    }
+}
+
+// Any angle, quarter turns are still done exactly so that the puzzle
+// instructions do not accumulate rounding errors.
+impl Vector for Point {
+    fn new(x: isize, y: isize) -> Self {
+        (x as f64, y as f64)
+    }
+
+    fn add_scaled(&mut self, (x, y): Self, factor: isize) -> Result<(), String> {
+        self.0 += x * factor as f64;
+        self.1 += y * factor as f64;
+        Ok(())
+    }
+
+    fn rotate(&mut self, degrees: isize) -> Result<(), String> {
+        let (x, y) = *self;
+        *self = match degrees.rem_euclid(360) {
+            0 => (x, y),
+            90 => (-y, x),
+            180 => (-x, -y),
+            270 => (y, -x),
+            degrees => {
+                let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+                (x * cos - y * sin, x * sin + y * cos)
+            }
+        };
+        Ok(())
+    }
+
+    fn to_point(self) -> Point {
+        self
+    }
+}
+
+fn overflow() -> String {
+    String::from("the position does not fit into an isize")
+}
+
+fn sub((x0, y0): Pos, (x1, y1): Pos) -> Result<Pos, String> {
+    x0.checked_sub(x1)
+        .zip(y0.checked_sub(y1))
+        .ok_or_else(overflow)
+}
+
+// The unit vector for N, E, S and W.
+fn compass(op: Op) -> Option<Pos> {
+    match op {
+        Op::N => Some((0, -1)),
+        Op::E => Some((1, 0)),
+        Op::S => Some((0, 1)),
+        Op::W => Some((-1, 0)),
+        _ => None,
+    }
+}
 
-    fn run(input: impl IntoIterator<Item = Input>) -> usize {
+pub(crate) trait Ship: Default {
+    type Vector: Vector;
+
+    fn apply(&mut self, dir: Dir) -> Result<(), String>;
+
+    fn position(&self) -> Self::Vector;
+
+    fn run(input: impl IntoIterator<Item = Input>) -> Result<Self, String> {
         let mut s = Self::default();
         for dir in input {
-            s.apply(dir);
+            s.apply(dir)?;
         }
-        s.dist()
+        Ok(s)
     }
 
-    fn dist(self) -> usize {
-        let (x, y) = self.into();
-        x.abs() as usize + y.abs() as usize
+    // Runs the instructions and records the position after each of them.
+    fn path(input: impl IntoIterator<Item = Input>) -> Result<Path, String> {
+        let mut s = Self::default();
+        let mut path = Path {
+            points: vec![s.position().to_point()],
+            instructions: Vec::new(),
+        };
+        for dir in input {
+            s.apply(dir)?;
+            path.points.push(s.position().to_point());
+            path.instructions.push(dir);
+        }
+        Ok(path)
     }
 }
 
-struct Ship1 {
-    head: Heading,
-    pos: Pos,
+// The ship moves towards its heading, which starts out east.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Ship1<V> {
+    head: V,
+    pos: V,
 }
 
-impl Default for Ship1 {
+impl<V: Vector> Default for Ship1<V> {
     fn default() -> Self {
         Self {
-            head: Heading::East,
-            pos: (0, 0),
+            head: V::new(1, 0),
+            pos: V::default(),
         }
     }
 }
 
-impl From<Ship1> for Pos {
-    fn from(s: Ship1) -> Self {
-        s.pos
-    }
-}
+impl<V: Vector> Ship for Ship1<V> {
+    type Vector = V;
 
-impl Ship for Ship1 {
-    fn apply(&mut self, dir: Dir) {
+    fn apply(&mut self, dir: Dir) -> Result<(), String> {
         match dir.op {
-            Op::N => Heading::North.apply(dir.num, &mut self.pos),
-            Op::E => Heading::East.apply(dir.num, &mut self.pos),
-            Op::S => Heading::South.apply(dir.num, &mut self.pos),
-            Op::W => Heading::West.apply(dir.num, &mut self.pos),
-            Op::L => self.head -= dir.num,
-            Op::R => self.head += dir.num,
-            Op::F => self.head.apply(dir.num, &mut self.pos),
+            Op::L => self.head.rotate(-dir.num),
+            Op::R => self.head.rotate(dir.num),
+            Op::F => self.pos.add_scaled(self.head, dir.num),
+            op => {
+                let (x, y) = compass(op).unwrap();
+                self.pos.add_scaled(V::new(x, y), dir.num)
+            }
         }
     }
+
+    fn position(&self) -> V {
+        self.pos
+    }
 }
 
-#[derive(Debug)]
-struct Ship2 {
-    pos: (isize, isize),
-    wp: (isize, isize),
+// The ship moves towards a waypoint that is relative to the ship.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Ship2<V> {
+    pos: V,
+    wp: V,
 }
 
-impl Default for Ship2 {
+impl<V: Vector> Default for Ship2<V> {
     fn default() -> Self {
         Self {
-            pos: (0, 0),
-            wp: (10, -1),
+            pos: V::default(),
+            wp: V::new(10, -1),
         }
     }
 }
 
-impl From<Ship2> for Pos {
-    fn from(s: Ship2) -> Self {
-        s.pos
-    }
-}
+impl<V: Vector> Ship for Ship2<V> {
+    type Vector = V;
 
-impl Ship for Ship2 {
-    fn apply(&mut self, dir: Dir) {
+    fn apply(&mut self, dir: Dir) -> Result<(), String> {
         match dir.op {
-            Op::N => Heading::North.apply(dir.num, &mut self.wp),
-            Op::E => Heading::East.apply(dir.num, &mut self.wp),
-            Op::S => Heading::South.apply(dir.num, &mut self.wp),
-            Op::W => Heading::West.apply(dir.num, &mut self.wp),
-            Op::L => Heading::from(dir.num).rotate_left(&mut self.wp),
-            Op::R => Heading::from(dir.num).rotate_right(&mut self.wp),
-            Op::F => {
-                self.pos.0 += self.wp.0 * dir.num;
-                self.pos.1 += self.wp.1 * dir.num;
+            Op::L => self.wp.rotate(-dir.num),
+            Op::R => self.wp.rotate(dir.num),
+            Op::F => self.pos.add_scaled(self.wp, dir.num),
+            op => {
+                let (x, y) = compass(op).unwrap();
+                self.wp.add_scaled(V::new(x, y), dir.num)
             }
         }
     }
+
+    fn position(&self) -> V {
+        self.pos
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
-#[repr(u8)]
-enum Heading {
-    North,
-    East,
-    South,
-    West,
+// Plans instructions for ships on the integer grid. All planned instructions
+// have positive numbers and turns are quarter turns.
+pub(crate) trait Plan: Ship<Vector = Pos> + PartialEq {
+    // Instruction lists that get the ship from its start to `target`,
+    // including the shortest one. Fails if no instructions fit into an isize.
+    fn plans(target: Pos) -> Result<Vec<Vec<Dir>>, String>;
+
+    // Instructions that give this ship the heading or waypoint of `other`
+    // without moving it.
+    fn align(&self, other: &Self) -> Result<Vec<Dir>, String>;
 }
 
-impl Heading {
-    fn apply(self, dist: isize, (x, y): &mut Pos) {
-        match self {
-            Heading::North => *y -= dist,
-            Heading::East => *x += dist,
-            Heading::South => *y += dist,
-            Heading::West => *x -= dist,
+impl Plan for Ship1<Pos> {
+    fn plans(target: Pos) -> Result<Vec<Vec<Dir>>, String> {
+        Ok(vec![shift(target)?])
+    }
+
+    fn align(&self, other: &Self) -> Result<Vec<Dir>, String> {
+        Ok(turn(self.head, other.head).into_iter().collect())
+    }
+}
+
+impl Plan for Ship2<Pos> {
+    fn plans(target: Pos) -> Result<Vec<Vec<Dir>>, String> {
+        if target == (0, 0) {
+            return Ok(vec![Vec::new()]);
+        }
+        // The ship only moves `k` times the waypoint at once, so the waypoint
+        // has to be `target / k`. Setting the waypoint takes at most two
+        // moves, so only the `k` that need a single turn or move can do better
+        // than `k = 1`. The largest `k` is kept for targets near the limits.
+        let start = Self::default();
+        let largest = gcd(target.0.unsigned_abs(), target.1.unsigned_abs());
+        // only 2^63 does not fit, its half still divides the target
+        let mut ks = vec![1];
+        ks.extend(isize::try_from(largest).or_else(|_| isize::try_from(largest / 2)));
+        let mut wp = start.wp;
+        for _ in 0..4 {
+            ks.extend(multiple(target, wp));
+            wp.rotate(90)?;
+        }
+        ks.extend(factor(target.0, start.wp.0).filter(|k| target.1 % k == 0));
+        ks.extend(factor(target.1, start.wp.1).filter(|k| target.0 % k == 0));
+        ks.sort_unstable();
+        ks.dedup();
+
+        let plan = |k: isize| -> Result<Vec<Dir>, String> {
+            let wp = (target.0 / k, target.1 / k);
+            let mut plan = match turn(start.wp, wp) {
+                Some(turn) => vec![turn],
+                None => shift(sub(wp, start.wp)?)?,
+            };
+            plan.push(Dir { op: Op::F, num: k });
+            Ok(plan)
+        };
+        let mut plans = Vec::new();
+        let mut error = None;
+        for k in ks {
+            match plan(k) {
+                Ok(plan) => plans.push(plan),
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) if plans.is_empty() => Err(e),
+            _ => Ok(plans),
         }
     }
 
-    // E+X => N-Y
-    // N-Y => W-X
-    // W-X => S+Y
-    // S+Y => E+X
-    fn rotate_left(self, pos: &mut Pos) {
-        for _ in 0..(self as u8) {
-            let (x, y) = *pos;
-            *pos = (y, -x);
+    fn align(&self, other: &Self) -> Result<Vec<Dir>, String> {
+        match turn(self.wp, other.wp) {
+            Some(turn) => Ok(vec![turn]),
+            None => shift(sub(other.wp, self.wp)?),
         }
     }
+}
+
+// The shortest instructions that move the ship from its start to `target`.
+pub(crate) fn plan<S: Plan>(target: Pos) -> Result<Vec<Dir>, String> {
+    Ok(S::plans(target)?
+        .into_iter()
+        .min_by_key(Vec::len)
+        .unwrap_or_default())
+}
 
-    // E+X => S+Y
-    // S+Y => W-X
-    // W-X => N-Y
-    // N-Y => E+X
-    fn rotate_right(self, pos: &mut Pos) {
-        for _ in 0..(self as u8) {
-            let (x, y) = *pos;
-            *pos = (-y, x);
+// Shortens the instructions so that the ship still ends up in the same
+// position with the same heading or waypoint. Instructions that cannot be
+// shortened are returned as they are.
+pub(crate) fn minimise<S: Plan>(input: &[Dir]) -> Result<Vec<Dir>, String> {
+    let end = S::run(input.iter().copied())?;
+    let mut shortest = input.to_vec();
+    // instructions that do not fit into an isize are never shorter
+    for mut plan in S::plans(end.position()).unwrap_or_default() {
+        match S::run(plan.iter().copied()).and_then(|ship| ship.align(&end)) {
+            Ok(align) => plan.extend(align),
+            Err(_) => continue,
+        }
+        if plan.len() < shortest.len() {
+            shortest = plan;
         }
     }
+    Ok(shortest)
+}
+
+// Whether the integer ships can follow the instructions.
+pub(crate) fn quarter_turns_only(input: &[Dir]) -> bool {
+    input
+        .iter()
+        .all(|dir| !matches!(dir.op, Op::L | Op::R) || dir.num % 90 == 0)
 }
 
-impl From<isize> for Heading {
-    fn from(x: isize) -> Self {
-        Self::from((x / 90) as u8)
+// Up to two compass moves, east or west first.
+fn shift((x, y): Pos) -> Result<Vec<Dir>, String> {
+    let dir = |num: isize, ahead: Op, back: Op| match num {
+        0 => Ok(None),
+        num if num > 0 => Ok(Some(Dir { op: ahead, num })),
+        num => num
+            .checked_neg()
+            .map(|num| Some(Dir { op: back, num }))
+            .ok_or_else(|| format!("cannot move {} in a single instruction", num)),
+    };
+    Ok(dir(x, Op::E, Op::W)?
+        .into_iter()
+        .chain(dir(y, Op::S, Op::N)?)
+        .collect())
+}
+
+// The single turn that rotates `from` onto `to`, if there is one.
+fn turn(from: Pos, to: Pos) -> Option<Dir> {
+    if from == to {
+        return None;
     }
+    [(Op::R, 90), (Op::R, 180), (Op::L, 90)]
+        .iter()
+        .map(|&(op, num)| Dir { op, num })
+        .find(|dir| {
+            let mut v = from;
+            let degrees = if let Op::L = dir.op {
+                -dir.num
+            } else {
+                dir.num
+            };
+            v.rotate(degrees).is_ok() && v == to
+        })
 }
 
-impl From<u8> for Heading {
-    fn from(x: u8) -> Self {
-        match x % 4 {
-            0 => Heading::North,
-            1 => Heading::East,
-            2 => Heading::South,
-            3 => Heading::West,
-            _ => unreachable!(),
-        }
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
 }
 
-impl AddAssign<isize> for Heading {
-    fn add_assign(&mut self, rhs: isize) {
-        *self = Self::from((*self as u8) + (Self::from(rhs) as u8))
+// The `k > 0` with `k * unit == n`, if there is one.
+fn factor(n: isize, unit: isize) -> Option<isize> {
+    if unit == 0 || n.checked_rem(unit)? != 0 {
+        return None;
     }
+    n.checked_div(unit).filter(|&k| k > 0)
 }
 
-impl SubAssign<isize> for Heading {
-    fn sub_assign(&mut self, rhs: isize) {
-        *self = Self::from(((*self as u8) + 4) - (Self::from(rhs) as u8));
+// The `k > 0` with `k * wp == target`, if there is one.
+fn multiple(target: Pos, wp: Pos) -> Option<isize> {
+    let k = factor(target.0, wp.0).or_else(|| factor(target.1, wp.1))?;
+    if wp.0.checked_mul(k)? == target.0 && wp.1.checked_mul(k)? == target.1 {
+        Some(k)
+    } else {
+        None
+    }
+}
+
+// The positions of a ship, starting before the first instruction.
+pub(crate) struct Path {
+    points: Vec<Point>,
+    instructions: Vec<Dir>,
+}
+
+impl Path {
+    pub(crate) fn end(&self) -> Point {
+        self.points[self.points.len() - 1]
+    }
+
+    // The length of all the straight lines between the positions.
+    pub(crate) fn travelled(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .sum()
+    }
+
+    // The smallest and the largest coordinates.
+    pub(crate) fn bbox(&self) -> (Point, Point) {
+        self.points.iter().fold(
+            (self.points[0], self.points[0]),
+            |((x0, y0), (x1, y1)), &(x, y)| ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))),
+        )
+    }
+
+    pub(crate) fn to_csv(&self) -> String {
+        let mut out = String::from("step,instruction,x,y\n");
+        for (step, (x, y)) in self.points.iter().enumerate() {
+            let instruction = step
+                .checked_sub(1)
+                .map_or_else(String::new, |i| self.instructions[i].to_string());
+            out.push_str(&format!("{},{},{},{}\n", step, instruction, x, y));
+        }
+        out
+    }
+
+    // Draws the path as a line from the green start to the red end.
+    pub(crate) fn to_svg(&self) -> String {
+        let ((x0, y0), (x1, y1)) = self.bbox();
+        let margin = ((x1 - x0).max(y1 - y0) / 20.0).max(1.0);
+        let points = self
+            .points
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (sx, sy) = self.points[0];
+        let (ex, ey) = self.end();
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" ",
+                "stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"/>\n",
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>\n",
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>\n",
+                "</svg>\n",
+            ),
+            x0 - margin,
+            y0 - margin,
+            x1 - x0 + 2.0 * margin,
+            y1 - y0 + 2.0 * margin,
+            points,
+            sx,
+            sy,
+            margin / 2.0,
+            ex,
+            ey,
+            margin / 2.0,
+        )
     }
 }
 
@@ -191,23 +484,45 @@ pub struct Dir {
     num: isize,
 }
 
-impl From<String> for Dir {
-    fn from(s: String) -> Self {
-        let op = match s.chars().next().unwrap() {
-            'N' => Op::N,
-            'E' => Op::E,
-            'S' => Op::S,
-            'W' => Op::W,
-            'L' => Op::L,
-            'R' => Op::R,
-            'F' => Op::F,
-            otherwise => unreachable!("Unexpected input: {}", otherwise),
+impl FromStr for Dir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let op = match chars.next() {
+            Some('N') => Op::N,
+            Some('E') => Op::E,
+            Some('S') => Op::S,
+            Some('W') => Op::W,
+            Some('L') => Op::L,
+            Some('R') => Op::R,
+            Some('F') => Op::F,
+            _ => return Err(format!("unknown instruction {}", s)),
         };
-        let num = s[1..].parse().unwrap();
-        if matches!(op, Op::L | Op::R) {
-            assert!(num % 90 == 0);
-        }
-        Self { op, num }
+        let num = chars
+            .as_str()
+            .parse::<isize>()
+            .ok()
+            .filter(|num| *num >= 0)
+            .ok_or_else(|| format!("invalid amount in {}", s))?;
+        Ok(Self { op, num })
+    }
+}
+
+// Parses one instruction per line, blank lines are skipped.
+pub(crate) fn parse_instructions(input: &str) -> Result<Vec<Dir>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i, l.trim()))
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(i, l)| l.parse().map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+impl fmt::Display for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}{}", self.op, self.num)
     }
 }
 
@@ -217,6 +532,7 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day12, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 820);
@@ -238,4 +554,167 @@ mod tests {
             )
         );
     }
+
+    const EX: &str = "
+        F10
+        N3
+        F7
+        R90
+        F11
+    ";
+
+    #[test]
+    fn test_path() {
+        let input = Solver::parse_input(EX);
+        let path = Ship1::<Pos>::path(input.iter().copied()).unwrap();
+        assert_eq!(
+            path.points,
+            vec![
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.0, -3.0),
+                (17.0, -3.0),
+                (17.0, -3.0),
+                (17.0, 8.0)
+            ]
+        );
+        assert_eq!(path.travelled(), 31.0);
+        assert_eq!(path.bbox(), ((0.0, -3.0), (17.0, 8.0)));
+        assert_eq!(
+            path.to_csv().lines().take(3).collect::<Vec<_>>(),
+            vec!["step,instruction,x,y", "0,,0,0", "1,F10,10,0"]
+        );
+        assert!(path
+            .to_svg()
+            .contains("points=\"0,0 10,0 10,-3 17,-3 17,-3 17,8\""));
+
+        let path = Ship2::<Point>::path(input).unwrap();
+        assert_eq!(path.end(), (214.0, 72.0));
+        assert_eq!(path.bbox(), ((0.0, -38.0), (214.0, 72.0)));
+    }
+
+    #[test]
+    fn test_any_angle() {
+        let input = Solver::parse_input("F10\nR45\nF10\nL135\nF5\n");
+        let (x, y) = Ship1::<Point>::run(input.iter().copied())
+            .unwrap()
+            .position();
+        let diag = 10.0 / 2_f64.sqrt();
+        assert!((x - (10.0 + diag)).abs() < 1e-9);
+        assert!((y - (diag - 5.0)).abs() < 1e-9);
+
+        let mut wp = Point::new(10, -1);
+        for _ in 0..8 {
+            wp.rotate(-45).unwrap();
+        }
+        assert!((wp.0 - 10.0).abs() < 1e-9 && (wp.1 + 1.0).abs() < 1e-9);
+        wp = Point::new(3, 4);
+        wp.rotate(-630).unwrap();
+        assert_eq!(wp, (-4.0, 3.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_instructions("F10\n\n  N3\nX7\n").err(),
+            Some(String::from("line 4: unknown instruction X7"))
+        );
+        assert_eq!(
+            parse_instructions("F10\nR").err(),
+            Some(String::from("line 2: invalid amount in R"))
+        );
+        assert!(parse_instructions("F-3").is_err());
+        assert!(parse_instructions("é90").is_err());
+        assert_eq!(parse_instructions(EX).map(|i| i.len()), Ok(5));
+    }
+
+    #[test]
+    fn test_quarter_turns_only() {
+        let input = Solver::parse_input("F10\nR45\nF10");
+        assert!(!quarter_turns_only(&input));
+        assert!(quarter_turns_only(&Solver::parse_input(EX)));
+        assert_eq!(
+            Ship2::<Pos>::run(input.iter().copied()).err(),
+            Some(String::from(
+                "integer positions can only turn by multiples of 90 degrees, not 45"
+            ))
+        );
+        assert!(Ship1::<Pos>::path(input.iter().copied()).is_err());
+        assert!(minimise::<Ship1<Pos>>(&input).is_err());
+        assert!(Ship1::<Point>::path(input).is_ok());
+    }
+
+    #[test]
+    fn test_plan() {
+        fn check<S: Plan>(target: Pos, len: usize) {
+            let plan = plan::<S>(target).unwrap();
+            assert_eq!(S::run(plan.iter().copied()).unwrap().position(), target);
+            assert_eq!(plan.len(), len, "{:?} for {:?}", plan, target);
+        }
+        check::<Ship1<Pos>>((0, 0), 0);
+        check::<Ship1<Pos>>((-5, 0), 1);
+        check::<Ship1<Pos>>((17, 8), 2);
+        check::<Ship2<Pos>>((0, 0), 0);
+        check::<Ship2<Pos>>((200, -20), 1);
+        check::<Ship2<Pos>>((-30, 3), 2);
+        check::<Ship2<Pos>>((2, 20), 2);
+        check::<Ship2<Pos>>((24, -6), 2);
+        check::<Ship2<Pos>>((3, 7), 3);
+        check::<Ship2<Pos>>((-1, 0), 3);
+
+        let (max, min) = (isize::MAX, isize::MIN);
+        check::<Ship1<Pos>>((max, min + 1), 2);
+        check::<Ship2<Pos>>((max, max), 3);
+        check::<Ship2<Pos>>((min, 0), 3);
+        check::<Ship2<Pos>>((max - 24, 0), 3);
+        check::<Ship2<Pos>>((min + 8, max - 7), 3);
+        assert!(plan::<Ship1<Pos>>((min, 0)).is_err());
+        assert!(plan::<Ship2<Pos>>((1, max)).is_err());
+        let far = parse_instructions("F9223372036854775807").unwrap();
+        assert!(Ship2::<Pos>::run(far.iter().copied()).is_err());
+        assert!(Ship2::<Point>::run(far).is_ok());
+    }
+
+    #[test]
+    fn test_minimise() {
+        let mut rng = aoc2020::Rng::new(12);
+        let ops = [Op::N, Op::E, Op::S, Op::W, Op::L, Op::R, Op::F];
+        for _ in 0..100 {
+            let input = (0..rng.range(0..=30))
+                .map(|_| {
+                    let op = ops[rng.below(7) as usize];
+                    let num = match op {
+                        Op::L | Op::R => 90 * rng.range(1..=3) as isize,
+                        _ => rng.range(1..=20) as isize,
+                    };
+                    Dir { op, num }
+                })
+                .collect::<Vec<_>>();
+
+            let min = minimise::<Ship1<Pos>>(&input).unwrap();
+            assert!(min.len() <= input.len().min(3));
+            assert_eq!(
+                Ship1::<Pos>::run(min.iter().copied()),
+                Ship1::run(input.iter().copied())
+            );
+
+            let min = minimise::<Ship2<Pos>>(&input).unwrap();
+            assert!(min.len() <= input.len());
+            assert_eq!(
+                Ship2::<Pos>::run(min.iter().copied()),
+                Ship2::run(input.iter().copied())
+            );
+        }
+        let minimise2 = |input: &str| {
+            minimise::<Ship2<Pos>>(&parse_instructions(input).unwrap())
+                .unwrap()
+                .iter()
+                .map(Dir::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(minimise2(EX), vec!["F10", "N3", "F7", "R90", "F11"]);
+        assert_eq!(minimise2("F1\nE2\nF2"), vec!["F1", "E2", "F2"]);
+        assert_eq!(minimise2("F1\nS1\nF1"), vec!["F1", "S1", "F1"]);
+        assert_eq!(minimise2("F2\nF3\nR90\nL90"), vec!["F5"]);
+    }
 }
@@ -1,53 +1,149 @@
-type Input = String;
+use aoc2020::crt::{self, Congruence};
+use std::{cmp::Reverse, collections::BinaryHeap, convert::TryFrom, fmt::Write, str::FromStr};
 
 register!(
     "input/day13.txt";
-    run(input: Input) -> i64 {
-        (
-            run1(&input),
-            run2(input)
-        )
+    run(input: verbatim aoc2020::Raw) -> i64 {
+        let timetable = input.parse::<Timetable>().unwrap_or_else(|e| panic!("{}", e));
+        (run1(&timetable), run2(&timetable))
     }
 );
 
-fn run1(input: &[Input]) -> i64 {
-    let time = input[0].parse::<i64>().unwrap();
-    input[1]
-        .split(',')
-        .map(str::parse::<i64>)
-        .filter_map(Result::ok)
-        .min_by_key(|t| t - (time % t))
-        .map(|bus| bus * (bus - (time % bus)))
-        .unwrap()
+fn run1(timetable: &Timetable) -> i64 {
+    let &(time, bus) = timetable
+        .next_departures(timetable.earliest, 1)
+        .first()
+        .expect("No bus departs before the end of the i64 range");
+    bus.checked_mul(time - timetable.earliest)
+        .expect("The answer does not fit into an i64")
+}
+
+fn run2(timetable: &Timetable) -> i64 {
+    let time = timetable
+        .first_in_pattern(&timetable.pattern())
+        .unwrap_or_else(|e| panic!("{}", e));
+    i64::try_from(time.residue).expect("The earliest time does not fit into an i64")
 }
 
-fn run2(input: Vec<Input>) -> i64 {
-    fn gcd(m: usize, n: usize) -> usize {
-        match n.checked_rem(m) {
-            None => n,
-            Some(n) => gcd(n, m),
+// The earliest time we can leave and the buses by their position in the
+// schedule, positions with an `x` are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Timetable {
+    pub(crate) earliest: i64,
+    buses: Vec<(usize, i64)>,
+}
+
+impl FromStr for Timetable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+        let earliest = lines.next().ok_or("missing earliest departure time")?;
+        let earliest = earliest
+            .parse()
+            .map_err(|_| format!("invalid earliest departure time {}", earliest))?;
+        let buses = lines
+            .next()
+            .ok_or("missing bus schedule")?
+            .split(',')
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, id)| *id != "x")
+            .map(|(pos, id)| match id.parse() {
+                Ok(bus) if bus > 0 => Ok((pos, bus)),
+                _ => Err(format!("invalid bus {} at position {}", id, pos + 1)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(line) = lines.next() {
+            return Err(format!("unexpected line {}", line));
+        }
+        if buses.is_empty() {
+            return Err(String::from("the schedule has no buses"));
         }
+        Ok(Self { earliest, buses })
     }
+}
 
-    fn lcm(a: usize, b: usize) -> usize {
-        a * b / gcd(a, b)
+impl Timetable {
+    pub(crate) fn buses(&self) -> impl Iterator<Item = i64> + '_ {
+        self.buses.iter().map(|&(_, bus)| bus)
     }
 
-    input[1]
-        .split(',')
-        .map(str::parse::<i64>)
-        .enumerate()
-        .filter_map(|(pos, id)| id.ok().map(move |id| (pos as i64, id)))
-        .fold((0, 1), |(time, step), (pos, bus)| {
-            (
-                (time..)
-                    .step_by(step)
-                    .find(|t| (t + pos) % bus == 0)
-                    .unwrap(),
-                lcm(step, bus as usize),
-            )
-        })
-        .0
+    // The buses with their position in the schedule as the offset.
+    pub(crate) fn pattern(&self) -> Vec<(i64, i64)> {
+        self.buses
+            .iter()
+            .map(|&(pos, bus)| (bus, pos as i64))
+            .collect()
+    }
+
+    // The next `n` departures at or after `time` as `(time, bus)`.
+    // Buses that leave at the same time are ordered by id. Departures
+    // after `i64::MAX` are left out.
+    pub(crate) fn next_departures(&self, time: i64, n: usize) -> Vec<(i64, i64)> {
+        let mut next = self
+            .buses()
+            .filter_map(|bus| {
+                let first = time.checked_add((bus - time.rem_euclid(bus)) % bus)?;
+                Some(Reverse((first, bus)))
+            })
+            .collect::<BinaryHeap<_>>();
+        let mut departures = Vec::new();
+        while departures.len() < n {
+            let (time, bus) = match next.pop() {
+                Some(Reverse(departure)) => departure,
+                None => break,
+            };
+            departures.push((time, bus));
+            if let Some(later) = time.checked_add(bus) {
+                next.push(Reverse((later, bus)));
+            }
+        }
+        departures
+    }
+
+    pub(crate) fn departing_at(&self, time: i64) -> Vec<i64> {
+        self.buses()
+            .filter(|bus| time.rem_euclid(*bus) == 0)
+            .collect()
+    }
+
+    // The times at which every `(bus, offset)` leaves `offset` minutes
+    // after the time, as the first such time and the period.
+    pub(crate) fn first_in_pattern(&self, pattern: &[(i64, i64)]) -> Result<Congruence, String> {
+        if let Some((bus, _)) = pattern
+            .iter()
+            .find(|(bus, _)| !self.buses().any(|b| b == *bus))
+        {
+            return Err(format!("bus {} is not in the timetable", bus));
+        }
+        crt::solve(
+            pattern
+                .iter()
+                .map(|&(bus, offset)| (-i128::from(offset), i128::from(bus))),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    // A table of the departures from `from` to `to`, one row per minute.
+    pub(crate) fn render(&self, from: i64, to: i64) -> String {
+        let mut out = String::from("time    ");
+        for bus in self.buses() {
+            let _ = write!(out, "  {:^8}", format!("bus {}", bus));
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        for time in from..=to {
+            let mut row = format!("{:<8}", time);
+            for bus in self.buses() {
+                let mark = if time.rem_euclid(bus) == 0 { 'D' } else { '.' };
+                let _ = write!(row, "  {:^8}", mark);
+            }
+            out.push_str(row.trim_end());
+            out.push('\n');
+        }
+        out
+    }
 }
 
 #[cfg(test)]
@@ -55,7 +151,12 @@ mod tests {
     use super::*;
     use aoc2020::Solution;
 
+    fn timetable(schedule: &str) -> Timetable {
+        format!("0\n{}", schedule).parse().unwrap()
+    }
+
     #[test]
+    #[cfg_attr(no_puzzle_input_day13, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 333);
@@ -77,33 +178,99 @@ mod tests {
 
     #[test]
     fn test_p2_0() {
-        assert_eq!(
-            1068781,
-            run2(vec![String::new(), String::from("7,13,x,x,59,x,31,19")])
-        );
+        assert_eq!(1068781, run2(&timetable("7,13,x,x,59,x,31,19")));
     }
 
     #[test]
     fn test_p2_1() {
+        assert_eq!(754018, run2(&timetable("67,7,59,61")));
+        assert_eq!(779210, run2(&timetable("67,x,7,59,61")));
+        assert_eq!(1261476, run2(&timetable("67,7,x,59,61")));
+    }
+
+    #[test]
+    #[should_panic(expected = "no number is")]
+    fn test_p2_unsolvable() {
+        run2(&timetable("4,6"));
+    }
+
+    #[test]
+    #[should_panic(expected = "No bus departs")]
+    fn test_p1_no_departure() {
+        run1(&"9223372036854775807\n2,4".parse().unwrap());
+    }
+
+    #[test]
+    fn test_p2_2() {
+        assert_eq!(1202161486, run2(&timetable("1789,37,47,1889")));
+    }
+
+    #[test]
+    fn test_queries() {
+        let timetable = "939\n7,13,x,x,59,x,31,19".parse::<Timetable>().unwrap();
+        assert_eq!(
+            timetable.next_departures(939, 4),
+            vec![(944, 59), (945, 7), (949, 13), (950, 19)]
+        );
+        assert_eq!(
+            timetable.next_departures(0, 3),
+            vec![(0, 7), (0, 13), (0, 19)]
+        );
+
+        // Departures stop at the end of the range, however many are asked for.
+        let late = i64::MAX - 40;
+        let expected = (late..=i64::MAX)
+            .flat_map(|t| {
+                timetable
+                    .departing_at(t)
+                    .into_iter()
+                    .map(move |bus| (t, bus))
+            })
+            .collect::<Vec<_>>();
+        assert!(!expected.is_empty());
+        assert_eq!(timetable.next_departures(late, usize::MAX), expected);
+        assert_eq!(timetable.next_departures(i64::MAX, 3), vec![(i64::MAX, 7)]);
+        assert_eq!(timetable.departing_at(1068781), vec![7]);
+        assert_eq!(timetable.departing_at(7 * 13 * 31), vec![7, 13, 31]);
+
+        let first = timetable.first_in_pattern(&[(7, 0), (13, 1)]).unwrap();
+        assert_eq!((first.residue, first.modulus), (77, 91));
+        assert!(timetable.first_in_pattern(&[(8, 0)]).is_err());
+        assert_eq!(
+            timetable
+                .first_in_pattern(&timetable.pattern())
+                .unwrap()
+                .residue,
+            1068781
+        );
+
+        let grid = timetable.render(1068781, 1068782);
+        let mut rows = grid.lines();
         assert_eq!(
-            754018,
-            run2(vec![String::new(), String::from("67,7,59,61")])
+            rows.next(),
+            Some("time       bus 7     bus 13    bus 59    bus 31    bus 19")
         );
         assert_eq!(
-            779210,
-            run2(vec![String::new(), String::from("67,x,7,59,61")])
+            rows.next(),
+            Some("1068781      D         .         .         .         .")
         );
         assert_eq!(
-            1261476,
-            run2(vec![String::new(), String::from("67,7,x,59,61")])
+            rows.next(),
+            Some("1068782      .         D         .         .         .")
         );
     }
 
     #[test]
-    fn test_p2_2() {
+    fn test_parse_errors() {
+        assert!("".parse::<Timetable>().is_err());
+        assert!("939".parse::<Timetable>().is_err());
+        assert!("soon\n7,13".parse::<Timetable>().is_err());
         assert_eq!(
-            1202161486,
-            run2(vec![String::new(), String::from("1789,37,47,1889")])
+            "939\n7,y,13".parse::<Timetable>(),
+            Err(String::from("invalid bus y at position 2"))
         );
+        assert!("939\n7,0".parse::<Timetable>().is_err());
+        assert!("939\nx,x".parse::<Timetable>().is_err());
+        assert!("939\n7\n13".parse::<Timetable>().is_err());
     }
 }
@@ -123,6 +123,7 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day14, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 8332632930672);
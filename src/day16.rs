@@ -1,3 +1,4 @@
+use aoc2020::{Generated, Generator, Rng};
 use std::{collections::HashSet, ops::RangeInclusive};
 
 type Input = String;
@@ -105,7 +106,14 @@ fn run2(input: Vec<Vec<Input>>) -> Output {
     let mut rules = rules.into_iter().map(|x| Some(x)).collect::<Vec<_>>();
     let mut rules_in_order = Vec::with_capacity(num_rules);
     rules_in_order.resize_with(rules.len(), || None::<Rule>);
+    span!(
+        Info,
+        "elimination",
+        rules = num_rules,
+        tickets = others.len()
+    );
     while solved.len() != num_rules {
+        event!(Debug, "round", solved = solved.len());
         for rule in &mut rules {
             if let Some(r) = rule {
                 let candidates = (0..num_rules)
@@ -114,6 +122,7 @@ fn run2(input: Vec<Vec<Input>>) -> Output {
                     .collect::<Vec<_>>();
 
                 if let &[idx] = &candidates[..] {
+                    event!(Trace, "assigned", column = idx, rule = r);
                     rules_in_order[idx] = rule.take();
                     solved.insert(idx);
                 } else {
@@ -133,18 +142,143 @@ fn run2(input: Vec<Vec<Input>>) -> Output {
         .product()
 }
 
+const FIELDS: [&str; 20] = [
+    "departure location",
+    "departure station",
+    "departure platform",
+    "departure track",
+    "departure date",
+    "departure time",
+    "arrival location",
+    "arrival station",
+    "arrival platform",
+    "arrival track",
+    "class",
+    "duration",
+    "price",
+    "route",
+    "row",
+    "seat",
+    "train",
+    "type",
+    "wagon",
+    "zone",
+];
+
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 20;
+
+    // Every field gets a rank and accepts the values from 1 up to the end of
+    // its own band of 50, except for a hole in the middle of that band. The
+    // column of the field with rank r only has values up to band r, with at
+    // least one in band r, so it is valid for exactly the fields of rank r or
+    // higher and the fields can be eliminated one rank at a time.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        const BAND: Output = 50;
+        assert!(size > 0, "need at least one field");
+
+        let value = |rng: &mut Rng, rank: Output, top_band: bool| loop {
+            let lo = if top_band { rank * BAND + 1 } else { 1 };
+            let v = rng.range(lo as u64..=((rank + 1) * BAND) as u64) as Output;
+            if v % BAND != BAND / 2 {
+                break v;
+            }
+        };
+
+        let mut names = (0..size)
+            .map(|i| match FIELDS.get(i) {
+                Some(name) => name.to_string(),
+                None => format!("extra {}", i),
+            })
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut names);
+        let mut rules = names
+            .iter()
+            .enumerate()
+            .map(|(rank, name)| {
+                format!(
+                    "{}: 1-{} or {}-{}",
+                    name,
+                    rank * BAND + BAND / 2 - 1,
+                    rank * BAND + BAND / 2 + 1,
+                    (rank + 1) * BAND
+                )
+            })
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut rules);
+
+        let mut ranks = (0..size).collect::<Vec<_>>();
+        rng.shuffle(&mut ranks);
+
+        let ticket = |rng: &mut Rng, top_band: bool| {
+            ranks
+                .iter()
+                .map(|&rank| value(rng, rank, top_band))
+                .collect::<Vec<_>>()
+        };
+        let format = |ticket: &[Output]| {
+            ticket
+                .iter()
+                .map(Output::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let mine = ticket(rng, false);
+        let mut error_rate = 0;
+        let mut nearby = vec![format(&ticket(rng, true))];
+        for i in 1..size.max(10) * 4 {
+            let mut t = ticket(rng, false);
+            if i % 4 == 0 {
+                let invalid = size * BAND + rng.range(1..=100) as Output;
+                let col = rng.below(size as u64) as usize;
+                t[col] = invalid;
+                error_rate += invalid;
+            }
+            nearby.push(format(&t));
+        }
+        rng.shuffle(&mut nearby);
+
+        let departure = ranks
+            .iter()
+            .zip(&mine)
+            .filter(|(&rank, _)| names[rank].starts_with("departure "))
+            .try_fold(1 as Output, |prod, (_, &v)| prod.checked_mul(v));
+
+        Generated {
+            input: format!(
+                "{}\n\nyour ticket:\n{}\n\nnearby tickets:\n{}\n",
+                rules.join("\n"),
+                format(&mine),
+                nearby.join("\n")
+            ),
+            part1: Some(error_rate.to_string()),
+            part2: departure.map(|d| d.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day16, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 18227);
         assert_eq!(res2, 2355350878831);
     }
 
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
     #[test]
     fn test_pt1() {
         assert_eq!(
@@ -35,6 +35,7 @@ fn run_any(input: &[Input], fourth_dim: bool) -> Output {
 }
 
 fn cycle(grid: &Grid, fourth_dim: bool) -> Grid {
+    event!(Debug, "cycle", alive = grid.len(), fourth_dim = fourth_dim);
     #[cfg(test)]
     {
         print_grid(grid);
@@ -102,6 +103,7 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day17, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 218);
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, iter::successors, iter::FromIterator};
+use aoc2020::{Generated, Generator, Rng};
+use std::{
+    collections::{HashMap, HashSet},
+    iter::successors,
+    iter::FromIterator,
+};
 
 type Input = String;
 type Output = u64;
@@ -203,6 +208,10 @@ const SEA_MONSTER: [&str; 3] = [
 ];
 
 fn count_monsters(dim: usize, image: Vec<Vec<u8>>) -> usize {
+    monster_counts(dim, image).find(|count| *count > 0).unwrap()
+}
+
+fn monster_counts(dim: usize, image: Vec<Vec<u8>>) -> impl Iterator<Item = usize> {
     let monster = SEA_MONSTER
         .iter()
         .enumerate()
@@ -215,13 +224,10 @@ fn count_monsters(dim: usize, image: Vec<Vec<u8>>) -> usize {
         .collect::<Vec<_>>();
 
     let flipped = flip(&image);
-    successors(Some(image), |img| Some(rotate(dim * 8, img.iter())))
+    successors(Some(image), move |img| Some(rotate(dim * 8, img.iter())))
         .take(4)
-        .chain(successors(Some(flipped), |img| Some(rotate(dim * 8, img.iter()))).take(4))
-        .map(|image| count_monsters_in(image, &monster))
-        .filter(|count| *count > 0)
-        .next()
-        .unwrap()
+        .chain(successors(Some(flipped), move |img| Some(rotate(dim * 8, img.iter()))).take(4))
+        .map(move |image| count_monsters_in(image, &monster))
 }
 
 fn count_monsters_in(image: Vec<Vec<u8>>, monster: &[(isize, isize)]) -> usize {
@@ -505,18 +511,165 @@ impl<'a> FromIterator<&'a str> for Tile {
     }
 }
 
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 12;
+
+    // All tiles are cut from one grid so that neighbours share their borders.
+    // Every border is chosen to be distinct and not a palindrome, so tiles only
+    // match their actual neighbours. The image itself is sparse noise with a
+    // few sea monsters placed on it, which makes accidental monsters unlikely.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        assert!((3..=12).contains(&size), "size must be between 3 and 12");
+
+        let len = 9 * size + 1;
+        let mut grid = vec![vec![false; len]; len];
+        for i in (0..len).step_by(9) {
+            for j in (0..len).step_by(9) {
+                grid[i][j] = rng.chance(1, 2);
+            }
+        }
+
+        let mut used = HashSet::new();
+        let mut border = |rng: &mut Rng, first: bool, last: bool| {
+            for _ in 0..10_000 {
+                let mut edge = [
+                    first, false, false, false, false, false, false, false, false, last,
+                ];
+                edge[1..9].iter_mut().for_each(|b| *b = rng.chance(1, 2));
+                let value = edge.iter().fold(0_u16, |e, &b| e << 1 | b as u16);
+                let swapped = TileEdges::swap(value);
+                if value != swapped && used.insert(value.min(swapped)) {
+                    return edge;
+                }
+            }
+            panic!("could not find a distinct tile border")
+        };
+        for i in (0..len).step_by(9) {
+            for j in (0..len - 1).step_by(9) {
+                let edge = border(rng, grid[i][j], grid[i][j + 9]);
+                (0..10).for_each(|k| grid[i][j + k] = edge[k]);
+                let edge = border(rng, grid[j][i], grid[j + 9][i]);
+                (0..10).for_each(|k| grid[j + k][i] = edge[k]);
+            }
+        }
+
+        // Monsters placed next to each other could still form another one in
+        // a different orientation, so the image is checked before using it.
+        let dim = 8 * size;
+        let (image, monsters) = loop {
+            let mut image = (0..dim)
+                .map(|_| (0..dim).map(|_| rng.chance(1, 10)).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let mut taken = vec![vec![false; dim]; dim];
+            let mut monsters = 0;
+            for _ in 0..(dim * dim / 150).max(1) {
+                let i = rng.below((dim - SEA_MONSTER.len() + 1) as u64) as usize;
+                let j = rng.below((dim - SEA_MONSTER[0].len() + 1) as u64) as usize;
+                let rows = i..i + SEA_MONSTER.len();
+                let cols = j..j + SEA_MONSTER[0].len();
+                if rows.clone().any(|i| cols.clone().any(|j| taken[i][j])) {
+                    continue;
+                }
+                for (mi, row) in SEA_MONSTER.iter().enumerate() {
+                    for (mj, b) in row.bytes().enumerate() {
+                        taken[i + mi][j + mj] = true;
+                        image[i + mi][j + mj] = b == b'#';
+                    }
+                }
+                monsters += 1;
+            }
+
+            let bytes = image
+                .iter()
+                .map(|row| row.iter().map(|&b| if b { b'#' } else { b'.' }).collect())
+                .collect();
+            let mut counts = monster_counts(size, bytes);
+            if counts.next() == Some(monsters) && counts.all(|count| count == 0) {
+                break (image, monsters);
+            }
+        };
+        for (i, row) in image.iter().enumerate() {
+            for (j, &b) in row.iter().enumerate() {
+                grid[i / 8 * 9 + 1 + i % 8][j / 8 * 9 + 1 + j % 8] = b;
+            }
+        }
+
+        let mut ids = HashSet::new();
+        while ids.len() < size * size {
+            ids.insert(rng.range(1000..=9999));
+        }
+        let mut ids = ids.into_iter().collect::<Vec<_>>();
+        ids.sort_unstable();
+        rng.shuffle(&mut ids);
+
+        let mut tiles = Vec::with_capacity(size * size);
+        for ti in 0..size {
+            for tj in 0..size {
+                let mut tile = grid[9 * ti..9 * ti + 10]
+                    .iter()
+                    .map(|row| row[9 * tj..9 * tj + 10].to_vec())
+                    .collect::<Vec<_>>();
+                for _ in 0..rng.below(4) {
+                    tile = (0..10)
+                        .map(|i| (0..10).map(|j| tile[9 - j][i]).collect())
+                        .collect();
+                }
+                if rng.chance(1, 2) {
+                    tile.reverse();
+                }
+                let tile = tile
+                    .iter()
+                    .map(|row| row.iter().map(|&b| if b { '#' } else { '.' }).collect())
+                    .collect::<Vec<String>>();
+                tiles.push(format!(
+                    "Tile {}:\n{}\n",
+                    ids[ti * size + tj],
+                    tile.join("\n")
+                ));
+            }
+        }
+        rng.shuffle(&mut tiles);
+
+        let corners = [0, size - 1, size * (size - 1), size * size - 1]
+            .iter()
+            .map(|&i| ids[i])
+            .product::<Output>();
+        let monster_size = SEA_MONSTER
+            .iter()
+            .flat_map(|line| line.bytes())
+            .filter(|b| *b == b'#')
+            .count();
+        let roughness = image.iter().flatten().filter(|b| **b).count() - monsters * monster_size;
+
+        Generated {
+            input: tiles.join("\n"),
+            part1: Some(corners.to_string()),
+            part2: Some(roughness.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day20, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 47213728755493);
         assert_eq!(res2, 1599);
     }
 
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, res2) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+        assert_eq!(Some(res2.to_string()), generated.part2);
+    }
+
     #[test]
     fn test_ex() {
         assert_eq!(
@@ -32,7 +32,9 @@ fn run(input: Vec<Input>) -> (Output, Output) {
 
     let mut confirmed = HashMap::new();
     let mut added = Vec::new();
+    span!(Info, "resolve", allergens = possible.len());
     while !possible.is_empty() {
+        event!(Debug, "round", unresolved = possible.len());
         for (allergen, ingredients) in &possible {
             let mut possible = ingredients
                 .iter()
@@ -45,6 +47,12 @@ fn run(input: Vec<Input>) -> (Output, Output) {
                 possible = &possible - &confirmed.values().copied().collect();
             }
 
+            event!(
+                Trace,
+                "candidates",
+                allergen = allergen,
+                ingredients = possible
+            );
             if possible.len() == 1 {
                 added.push(*allergen);
                 confirmed.insert(*allergen, possible.into_iter().next().unwrap());
@@ -78,6 +86,7 @@ mod tests {
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day21, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1.as_str(), "2170");
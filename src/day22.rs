@@ -1,5 +1,6 @@
+use aoc2020::{Generated, Generator, Rng};
 use std::{
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     hash::{Hash, Hasher},
     iter::FromIterator,
 };
@@ -138,7 +139,12 @@ fn run(input: Vec<Vec<Input>>) -> (Output, Output) {
     let player1 = players.next().unwrap();
     let player2 = players.next().unwrap();
 
-    (run1(player1, player2), run2(player1, player2))
+    let part1 = {
+        span!(Info, "part1", cards = player1.len() + player2.len());
+        run1(player1, player2)
+    };
+    span!(Info, "part2");
+    (part1, run2(player1, player2))
 }
 
 fn run1(mut player1: Deck, mut player2: Deck) -> Output {
@@ -169,9 +175,11 @@ fn run2(mut player1: Deck, mut player2: Deck) -> Output {
 }
 
 fn play_round(pl1: &mut Deck, pl2: &mut Deck) -> bool {
+    span!(Debug, "game", player1 = pl1.len(), player2 = pl2.len());
     let mut played = HashSet::new();
     while !pl1.is_empty() && !pl2.is_empty() {
         if !played.insert(hash(pl1, pl2)) {
+            event!(Debug, "repeated", rounds = played.len());
             return true;
         }
 
@@ -186,12 +194,19 @@ fn play_round(pl1: &mut Deck, pl2: &mut Deck) -> bool {
             p1 > p2
         };
 
+        event!(Trace, "round", card1 = p1, card2 = p2, player1_won = p1_won);
         if p1_won {
             pl1.extend(p1, p2);
         } else {
             pl2.extend(p2, p1);
         }
     }
+    event!(
+        Debug,
+        "winner",
+        player1 = pl2.is_empty(),
+        rounds = played.len()
+    );
     pl2.is_empty()
 }
 
@@ -209,18 +224,76 @@ fn eval(deck: &Deck) -> Output {
         .sum()
 }
 
+impl Generator for Solver {
+    const DEFAULT_SIZE: usize = 25;
+
+    // A plain game of Combat can go on forever, so the decks are reshuffled
+    // until a game finishes within a bounded number of rounds.
+    fn generate(rng: &mut Rng, size: usize) -> Generated {
+        assert!(
+            (1..=25).contains(&size),
+            "deck size must be between 1 and 25"
+        );
+
+        let mut cards = (1..=2 * size).collect::<Vec<_>>();
+        let score = loop {
+            rng.shuffle(&mut cards);
+            let mut p1 = cards[..size].iter().copied().collect::<VecDeque<_>>();
+            let mut p2 = cards[size..].iter().copied().collect::<VecDeque<_>>();
+            let mut rounds = 0;
+            while !p1.is_empty() && !p2.is_empty() && rounds < 100_000 {
+                let (c1, c2) = (p1.pop_front().unwrap(), p2.pop_front().unwrap());
+                if c1 > c2 {
+                    p1.extend([c1, c2]);
+                } else {
+                    p2.extend([c2, c1]);
+                }
+                rounds += 1;
+            }
+            if p1.is_empty() || p2.is_empty() {
+                let winner = if p1.is_empty() { p2 } else { p1 };
+                break winner
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(i, c)| (i + 1) * c)
+                    .sum::<Output>();
+            }
+        };
+
+        let deck = |cards: &[usize]| cards.iter().map(|c| format!("{}\n", c)).collect::<String>();
+        Generated {
+            input: format!(
+                "Player 1:\n{}\nPlayer 2:\n{}",
+                deck(&cards[..size]),
+                deck(&cards[size..])
+            ),
+            part1: Some(score.to_string()),
+            part2: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use aoc2020::Solution;
 
     #[test]
+    #[cfg_attr(no_puzzle_input_day22, ignore = "puzzle input not available")]
     fn test() {
         let (res1, res2) = Solver::run_on_input();
         assert_eq!(res1, 29764);
         assert_eq!(res2, 32588);
     }
 
+    #[test]
+    fn test_generated() {
+        let generated = Solver::generate(&mut Rng::new(2020), Solver::DEFAULT_SIZE);
+        let (res1, _) = Solver::run_on(&generated.input);
+        assert_eq!(Some(res1.to_string()), generated.part1);
+    }
+
     #[test]
     fn test_pt1() {
         assert_eq!(
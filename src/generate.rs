@@ -0,0 +1,73 @@
+use crate::Solution;
+use std::ops::RangeInclusive;
+
+pub struct Rng(u64);
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    // splitmix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    pub fn range(&mut self, range: RangeInclusive<u64>) -> u64 {
+        range.start() + self.below(range.end() - range.start() + 1)
+    }
+
+    pub fn chance(&mut self, num: u64, den: u64) -> bool {
+        self.below(den) < num
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Generated {
+    pub input: String,
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+}
+
+pub trait Generator: Solution {
+    const DEFAULT_SIZE: usize;
+
+    fn generate(rng: &mut Rng, size: usize) -> Generated;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible() {
+        let a = (0..8).scan(Rng::new(42), |r, _| Some(r.next_u64()));
+        let b = (0..8).scan(Rng::new(42), |r, _| Some(r.next_u64()));
+        assert!(a.eq(b));
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            seen[rng.range(3..=5) as usize - 3] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+    }
+}
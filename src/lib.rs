@@ -1,4 +1,25 @@
-use std::{fmt::Debug, fmt::Display, marker::PhantomData, str::FromStr};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    fmt::Display,
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub mod cache;
+pub mod crt;
+pub mod crypt;
+pub mod generate;
+pub mod stream;
+pub mod trace;
+pub mod watch;
+
+pub use aoc2020_derive::aoc;
+pub use cache::Cache;
+pub use generate::{Generated, Generator, Rng};
+pub use stream::{StreamInput, StreamingSolution};
 
 pub trait PuzzleInput
 where
@@ -59,14 +80,40 @@ where
     }
 }
 
+pub struct Raw;
+
+impl PuzzleInput for Raw {
+    type Out = String;
+
+    fn from_input(input: &str) -> Self::Out {
+        String::from(input)
+    }
+}
+
 pub trait Solution {
     type Input: PuzzleInput;
     type Output;
 
     fn puzzle_input() -> &'static str;
 
+    fn input_file() -> &'static str;
+
     fn run(input: <Self::Input as PuzzleInput>::Out) -> (Self::Output, Self::Output);
 
+    #[inline]
+    fn input_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join(Self::input_file())
+    }
+
+    // Reads the input from disk, so that it can change without recompiling.
+    // Falls back to the compiled in input if the file is not available.
+    fn load_input() -> Cow<'static, str> {
+        fs::read_to_string(Self::input_path())
+            .map_or_else(|_| Cow::Borrowed(Self::puzzle_input()), Cow::Owned)
+    }
+
     #[inline]
     fn parse_input(input: &str) -> <Self::Input as PuzzleInput>::Out {
         <Self::Input as PuzzleInput>::from_input(input)
@@ -74,7 +121,11 @@ pub trait Solution {
 
     #[inline]
     fn run_on(input: &str) -> (Self::Output, Self::Output) {
-        let input = Self::parse_input(input);
+        let input = {
+            span!(Info, "parse", bytes = input.len());
+            Self::parse_input(input)
+        };
+        span!(Info, "run");
         Self::run(input)
     }
 
@@ -92,6 +143,21 @@ pub trait Solution {
         let (res1, res2) = Self::run_on_input();
         (Box::new(res1), Box::new(res2))
     }
+
+    fn solve_cached(day: u8, cache: &Cache) -> (Box<dyn Display>, Box<dyn Display>)
+    where
+        Self::Output: Display + 'static,
+    {
+        let input = Self::puzzle_input();
+        if let (Some(res1), Some(res2)) = (cache.get(day, 1, input), cache.get(day, 2, input)) {
+            return (Box::new(res1), Box::new(res2));
+        }
+
+        let (res1, res2) = Self::run_on(input);
+        cache.put(day, 1, input, &res1.to_string());
+        cache.put(day, 2, input, &res2.to_string());
+        (Box::new(res1), Box::new(res2))
+    }
 }
 
 #[macro_export]
@@ -121,7 +187,19 @@ macro_rules! register {
 
             #[inline]
             fn puzzle_input() -> &'static str {
-                ::std::include_str!($file)
+                #[cfg(not(feature = "encrypted-inputs"))]
+                {
+                    ::std::include_str!($file)
+                }
+                #[cfg(feature = "encrypted-inputs")]
+                {
+                    ::std::include_str!(::std::concat!(::std::env!("OUT_DIR"), "/", $file))
+                }
+            }
+
+            #[inline]
+            fn input_file() -> &'static str {
+                $file
             }
 
             #[inline]
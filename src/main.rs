@@ -2,7 +2,17 @@
 //
 #[macro_use]
 extern crate aoc2020;
-use aoc2020::Solution;
+use aoc2020::{
+    crypt, trace, watch, Cache, Generated, Generator, Parsing, Rng, Solution, StreamInput,
+    StreamingSolution,
+};
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufRead},
+    path::Path,
+    time::SystemTime,
+};
 
 mod day01;
 mod day02;
@@ -31,36 +41,651 @@ mod day24;
 mod day25;
 
 fn main() {
-    for day in std::env::args()
-        .skip(1)
-        .filter_map(|s| s.parse::<u8>().ok())
-    {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("stream") => stream(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("encrypt") => encrypt(),
+        Some("watch") => watch(&args[1..]),
+        Some("ksum") => ksum(&args[1..]),
+        Some("passwords") => passwords(&args[1..]),
+        Some("slopes") => slopes(&args[1..]),
+        Some("passports") => passports(&args[1..]),
+        Some("seats") => seats(&args[1..]),
+        Some("answers") => answers(&args[1..]),
+        Some("bags") => bags(&args[1..]),
+        Some("debug") => debug(&args[1..]),
+        Some("repair") => repair(&args[1..]),
+        Some("xmas") => xmas(&args[1..]),
+        Some("adapters") => adapters(&args[1..]),
+        Some("seating") => seating(&args[1..]),
+        Some("navigate") => navigate(&args[1..]),
+        Some("plan") => plan(&args[1..]),
+        Some("buses") => buses(&args[1..]),
+        // clearing removes the answers of every build
+        Some("clear-cache") => Cache::new(Cache::default_dir(), 0)
+            .clear()
+            .expect("Could not clear the cache"),
+        _ => run(&args),
+    }
+}
+
+fn stream(args: &[String]) {
+    let day = args
+        .first()
+        .and_then(|s| s.parse::<u8>().ok())
+        .expect("Usage: stream <day> [file]");
+    let (res1, res2) = match args.get(1) {
+        Some(file) => solve_reader(day, File::open(file).expect("Could not open input")),
+        None => solve_reader(day, io::stdin().lock()),
+    };
+
+    println!("Day {:02} Part 1:\t{}", day, res1);
+    println!("Day {:02} Part 2:\t{}", day, res2);
+}
+
+fn solve_reader(day: u8, reader: impl io::Read) -> (Box<dyn Display>, Box<dyn Display>) {
+    match day {
+        1 => day01::Solver::solve_reader(reader),
+        9 => day09::Solver::solve_reader(reader),
+        10 => day10::Solver::solve_reader(reader),
+        x => {
+            eprintln!("Day {} does not support streaming, try days 1, 9 or 10", x);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn generate(args: &[String]) {
+    let day = args
+        .first()
+        .and_then(|s| s.parse::<u8>().ok())
+        .expect("Usage: generate <day> [--seed <seed>] [--size <size>]");
+    let seed = option(args, "--seed").map_or(2020, |s| s.parse().expect("Invalid seed"));
+    let size = option(args, "--size").map(|s| s.parse().expect("Invalid size"));
+
+    let mut rng = Rng::new(seed);
+    let generated = match day {
+        1 => generate_with::<day01::Solver>(&mut rng, size),
+        7 => generate_with::<day07::Solver>(&mut rng, size),
+        8 => generate_with::<day08::Solver>(&mut rng, size),
+        10 => generate_with::<day10::Solver>(&mut rng, size),
+        16 => generate_with::<day16::Solver>(&mut rng, size),
+        20 => generate_with::<day20::Solver>(&mut rng, size),
+        22 => generate_with::<day22::Solver>(&mut rng, size),
+        x => {
+            eprintln!("Day {} has no input generator", x);
+            std::process::exit(2);
+        }
+    };
+
+    print!("{}", generated.input);
+    if let Some(res1) = generated.part1 {
+        eprintln!("Day {:02} Part 1:\t{}", day, res1);
+    }
+    if let Some(res2) = generated.part2 {
+        eprintln!("Day {:02} Part 2:\t{}", day, res2);
+    }
+}
+
+fn generate_with<G: Generator>(rng: &mut Rng, size: Option<usize>) -> Generated {
+    G::generate(rng, size.unwrap_or(G::DEFAULT_SIZE))
+}
+
+fn encrypt() {
+    let key =
+        std::env::var(crypt::KEY_ENV).unwrap_or_else(|_| panic!("{} is not set", crypt::KEY_ENV));
+    let key = crypt::parse_key(&key).unwrap_or_else(|e| panic!("{}", e));
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+
+    let inputs = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/input");
+    for day in 1..=25 {
+        let plain = inputs.join(format!("day{}.txt", day));
+        let input = match fs::read(&plain) {
+            Ok(input) => input,
+            Err(_) => continue,
+        };
+        let mut rng = Rng::new(now ^ day);
+        let mut nonce = [0; 12];
+        nonce[..8].copy_from_slice(&rng.next_u64().to_le_bytes());
+        nonce[8..].copy_from_slice(&rng.next_u64().to_le_bytes()[..4]);
+
+        let encrypted = plain.with_extension("txt.enc");
+        fs::write(&encrypted, crypt::encrypt(&key, nonce, &input)).unwrap();
+        println!("{}", encrypted.display());
+    }
+}
+
+fn ksum(args: &[String]) {
+    let target = option(args, "--target").map_or(2020, |s| s.parse().expect("Invalid target"));
+    let k = option(args, "--k").map_or(2, |s| s.parse().expect("Invalid k"));
+    let strategy = option(args, "--strategy").map_or(day01::Strategy::TwoPointer, |s| {
+        s.parse().unwrap_or_else(|e| panic!("{}", e))
+    });
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day01::Solver::load_input().into_owned(),
+    };
+
+    let items = day01::Solver::parse_input(&input);
+    let solutions = day01::k_sums(target, k, &items, strategy);
+    if solutions.is_empty() {
+        println!("No {} entries sum to {}", k, target);
+        std::process::exit(1);
+    }
+    for solution in solutions {
+        let product = solution
+            .iter()
+            .try_fold(1_u64, |prod, &item| prod.checked_mul(item))
+            .map_or_else(|| String::from("overflow"), |p| p.to_string());
+        let terms = solution
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(" + ");
+        println!("{} = {}\tproduct {}", terms, target, product);
+    }
+}
+
+fn passwords(args: &[String]) {
+    let policy = option(args, "--policy").map_or_else(day02::Rule::sled_rental, |s| {
+        s.parse().unwrap_or_else(|e| panic!("{}", e))
+    });
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day02::Solver::load_input().into_owned(),
+    };
+
+    let input = day02::Solver::parse_input(&input);
+    let mut invalid = 0;
+    for (line, entry, reason) in day02::report(&input, &policy) {
+        println!("{:>4}: {}\t{}", line, entry, reason);
+        invalid += 1;
+    }
+    println!(
+        "{} of {} passwords are valid under {}",
+        input.len() - invalid,
+        input.len(),
+        policy
+    );
+}
+
+fn slopes(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day03::Solver::load_input().into_owned(),
+    };
+    let lines = day03::Solver::parse_input(&input);
+    let parse_slope = |s: &str| s.parse().unwrap_or_else(|e| panic!("{}", e));
+
+    if let Some(slope) = option(args, "--render") {
+        print!("{}", day03::render(parse_slope(slope), &lines));
+        return;
+    }
+    if let Some(max) = option(args, "--fewest") {
+        match day03::fewest_trees(parse_slope(max), &lines) {
+            Some((slope, trees)) => println!("{}\t{} trees", slope, trees),
+            None => println!("No slopes within {}", max),
+        }
+        return;
+    }
+
+    let slopes = match (option(args, "--slopes"), option(args, "--slopes-file")) {
+        (Some(slopes), _) => day03::parse_slopes(slopes),
+        (None, Some(file)) => {
+            day03::parse_slopes(&fs::read_to_string(file).expect("Could not read slopes"))
+        }
+        (None, None) => Ok(day03::PART2_SLOPES.to_vec()),
+    };
+    let slopes = slopes.unwrap_or_else(|e| panic!("{}", e));
+    let counts = day03::trees_per_slope(&slopes, &lines);
+    for (slope, trees) in &counts {
+        println!("{}\t{} trees", slope, trees);
+    }
+    println!(
+        "product\t{}",
+        counts.iter().map(|(_, trees)| trees).product::<usize>()
+    );
+}
+
+fn passports(args: &[String]) {
+    let schema = match option(args, "--schema") {
+        None | Some("strict") => day04::Schema::strict(),
+        Some("presence") => day04::Schema::presence(),
+        Some(file) => fs::read_to_string(file)
+            .expect("Could not read schema")
+            .parse()
+            .unwrap_or_else(|e| panic!("{}", e)),
+    };
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day04::Solver::load_input().into_owned(),
+    };
+
+    let passports = day04::parse_passports(day04::Solver::parse_input(&input));
+    let mut valid = 0;
+    for (index, passport) in passports.iter().enumerate() {
+        match schema.validate(passport) {
+            Ok(()) => valid += 1,
+            Err(failures) => {
+                println!("passport {}:", index + 1);
+                for failure in failures {
+                    println!("    {}", failure);
+                }
+            }
+        }
+    }
+    println!("{} of {} passports are valid", valid, passports.len());
+}
+
+fn seats(args: &[String]) {
+    let bits =
+        |name, default| option(args, name).map_or(default, |s| s.parse().expect("Invalid bits"));
+    let codec = day05::Codec::new(
+        option(args, "--rows").unwrap_or("FB"),
+        bits("--row-bits", 7),
+        option(args, "--cols").unwrap_or("LR"),
+        bits("--col-bits", 3),
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    if let Some(id) = option(args, "--encode") {
+        let id = id.parse::<u64>().expect("Invalid seat id");
+        match codec.encode(day05::Seat::from(id)) {
+            Ok(pass) => println!("{}", pass),
+            Err(e) => panic!("{}", e),
+        }
+        return;
+    }
+
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day05::Solver::load_input().into_owned(),
+    };
+    let seats = input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| codec.decode(l))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    if args.iter().any(|a| a == "--map") {
+        print!("{}", codec.occupancy_map(&seats));
+        return;
+    }
+    for seat in day05::empty_seats(&codec, &seats) {
+        let (row, col) = codec.row_col(seat);
+        println!(
+            "{}\trow {} col {}\t{}",
+            *seat,
+            row,
+            col,
+            codec.encode(seat).unwrap()
+        );
+    }
+}
+
+fn answers(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day06::Solver::load_input().into_owned(),
+    };
+    let groups = day06::Solver::parse_input(&input);
+
+    if args.iter().any(|a| a == "--histogram") {
+        let mut totals = std::collections::BTreeMap::<char, usize>::new();
+        for group in &groups {
+            for (answer, count) in day06::histogram(group) {
+                *totals.entry(answer).or_default() += count;
+            }
+        }
+        let max = totals.values().copied().max().unwrap_or(1);
+        for (answer, count) in totals {
+            println!("{}\t{:>5} {}", answer, count, "#".repeat(count * 50 / max));
+        }
+        return;
+    }
+
+    let total = |f: &dyn Fn(&[day06::Answers]) -> day06::Answers| {
+        groups.iter().map(|g| f(g).len()).sum::<usize>()
+    };
+    println!("anyone\t{}", total(&day06::anyone));
+    println!("everyone\t{}", total(&day06::everyone));
+    println!("odd\t{}", total(&day06::odd_ones));
+    if let Some(k) = option(args, "--at-least") {
+        let k = k.parse().expect("Invalid k");
+        println!("at least {}\t{}", k, total(&|g| day06::at_least(g, k)));
+    }
+}
+
+fn bags(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day07::Solver::load_input().into_owned(),
+    };
+    let graph = day07::BagGraph::new(day07::Solver::parse_input(&input))
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    let result = if let Some(bag) = option(args, "--containers") {
+        graph.containers(bag).map(|bags| {
+            for bag in &bags {
+                println!("{}", bag);
+            }
+            println!("{} bags can contain {}", bags.len(), bag);
+        })
+    } else if let Some(bag) = option(args, "--inside") {
+        graph
+            .count_inside(bag)
+            .map(|count| println!("{} contains {} bags", bag, count))
+    } else if args.iter().any(|a| a == "--dot") {
+        graph
+            .to_dot(option(args, "--root"))
+            .map(|dot| print!("{}", dot))
+    } else {
+        eprintln!("Usage: bags [--input file] (--containers <bag> | --inside <bag> | --dot [--root <bag>])");
+        std::process::exit(2);
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn debug(args: &[String]) {
+    let program = load_program(args);
+    if args.iter().any(|a| a == "--list") {
+        print!("{}", day08::disassemble(&program));
+        return;
+    }
+    let stdin = std::io::stdin();
+    day08::debug(&program, stdin.lock(), std::io::stdout()).expect("Could not run debugger");
+}
+
+fn repair(args: &[String]) {
+    let program = load_program(args);
+    let repairs = day08::repairs(&program).unwrap_or_else(|e| panic!("{}", e));
+    if repairs.is_empty() {
+        println!("No single flip makes the program terminate");
+        std::process::exit(1);
+    }
+    for repair in repairs {
+        println!(
+            "{:>5}: {} -> {}\tacc {}",
+            repair.pc,
+            program.display(&program.instructions()[repair.pc]),
+            program.display(&repair.instruction),
+            repair.acc
+        );
+    }
+}
+
+fn xmas(args: &[String]) {
+    let preamble = option(args, "--preamble")
+        .map_or(day09::PREAMBLE, |s| s.parse().expect("Invalid preamble"));
+    let reader: Box<dyn BufRead> = match option(args, "--input") {
+        Some("-") => Box::new(io::stdin().lock()),
+        Some(file) => Box::new(io::BufReader::new(
+            File::open(file).expect("Could not open input"),
+        )),
+        None => Box::new(io::Cursor::new(day09::Solver::load_input().into_owned())),
+    };
+    let input = <Parsing<u64> as StreamInput>::from_reader(reader);
+
+    if args.iter().any(|a| a == "--all") {
+        for (pos, num) in day09::invalid_numbers(input, preamble) {
+            println!("{}\t{}", pos, num);
+        }
+        return;
+    }
+    let (res1, res2) = day09::run_stream(input, preamble);
+    println!("Day 09 Part 1:\t{}", res1);
+    println!("Day 09 Part 2:\t{}", res2);
+}
+
+fn adapters(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day10::Solver::load_input().into_owned(),
+    };
+    let max_step = option(args, "--max-step")
+        .map_or(day10::MAX_STEP, |s| s.parse().expect("Invalid max step"));
+    let chain = day10::Chain::new(day10::Solver::parse_input(&input), max_step);
+
+    match option(args, "--list") {
+        Some(limit) => {
+            let limit = limit.parse().expect("Invalid limit");
+            for arrangement in chain.enumerate().take(limit) {
+                let arrangement = arrangement.iter().map(u64::to_string).collect::<Vec<_>>();
+                println!("{}", arrangement.join(","));
+            }
+        }
+        None => println!("{}", chain.arrangements()),
+    }
+}
+
+fn seating(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day11::Solver::load_input().into_owned(),
+    };
+    let rules = match (option(args, "--part"), option(args, "--rules")) {
+        (Some(_), Some(_)) => panic!("Use either --part or --rules"),
+        (None, None) | (Some("1"), None) => day11::Rules::PART1,
+        (Some("2"), None) => day11::Rules::PART2,
+        (Some(part), None) => panic!("Unknown part {}, expected 1 or 2", part),
+        (None, Some(rules)) => rules.parse().unwrap_or_else(|e| panic!("{}", e)),
+    };
+    let layout = day11::Layout::new(&day11::Solver::parse_input(&input), &rules)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut simulation = day11::Simulation::new(&layout, &rules);
+    let stats = day11::run_to_end(&mut simulation);
+
+    for (round, changed) in stats.changes.iter().enumerate() {
+        println!("round {}\t{} changed", round + 1, changed);
+    }
+    println!("seats\t{}", layout.seats());
+    println!("rules\t{}", rules);
+    println!("rounds\t{}", stats.changes.len());
+    if let day11::End::Oscillating { start, period } = stats.end {
+        println!("oscillates\tfrom round {} every {} rounds", start, period);
+    }
+    println!("occupied\t{}", stats.occupied);
+    if args.iter().any(|a| a == "--show") {
+        print!("{}", simulation.render());
+    }
+}
+
+fn navigate(args: &[String]) {
+    use day12::{Point, Pos, Ship, Ship1, Ship2};
+
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day12::Solver::load_input().into_owned(),
+    };
+    let input = day12::parse_instructions(&input).unwrap_or_else(|e| panic!("{}", e));
+    // Turns that are not quarter turns need floating point positions.
+    let float = args.iter().any(|a| a == "--float") || !day12::quarter_turns_only(&input);
+    let path = match (option(args, "--ship"), float) {
+        (None, false) | (Some("1"), false) => Ship1::<Pos>::path(input),
+        (None, true) | (Some("1"), true) => Ship1::<Point>::path(input),
+        (Some("2"), false) => Ship2::<Pos>::path(input),
+        (Some("2"), true) => Ship2::<Point>::path(input),
+        (Some(ship), _) => panic!("Unknown ship {}, expected 1 or 2", ship),
+    }
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    if args.iter().any(|a| a == "--csv") {
+        print!("{}", path.to_csv());
+    } else if args.iter().any(|a| a == "--svg") {
+        print!("{}", path.to_svg());
+    } else {
+        let (x, y) = path.end();
+        let ((x0, y0), (x1, y1)) = path.bbox();
+        println!("end\t{},{}", x, y);
+        println!("manhattan\t{}", x.abs() + y.abs());
+        println!("travelled\t{:.3}", path.travelled());
+        println!("bbox\t{},{} to {},{}", x0, y0, x1, y1);
+    }
+}
+
+fn plan(args: &[String]) {
+    use day12::{Pos, Ship1, Ship2};
+
+    let ship = option(args, "--ship").unwrap_or("1");
+    let instructions = match option(args, "--to") {
+        Some(target) => {
+            let (x, y) = target
+                .split_once(',')
+                .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                .expect("Invalid target, expected x,y");
+            match ship {
+                "1" => day12::plan::<Ship1<Pos>>((x, y)),
+                "2" => day12::plan::<Ship2<Pos>>((x, y)),
+                _ => panic!("Unknown ship {}, expected 1 or 2", ship),
+            }
+            .unwrap_or_else(|e| panic!("{}", e))
+        }
+        None => {
+            let input = match option(args, "--input") {
+                Some(file) => fs::read_to_string(file).expect("Could not read input"),
+                None => day12::Solver::load_input().into_owned(),
+            };
+            let input = day12::parse_instructions(&input).unwrap_or_else(|e| panic!("{}", e));
+            match ship {
+                "1" => day12::minimise::<Ship1<Pos>>(&input),
+                "2" => day12::minimise::<Ship2<Pos>>(&input),
+                _ => panic!("Unknown ship {}, expected 1 or 2", ship),
+            }
+            .unwrap_or_else(|e| panic!("{}", e))
+        }
+    };
+    for instruction in instructions {
+        println!("{}", instruction);
+    }
+}
+
+fn buses(args: &[String]) {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day13::Solver::load_input().into_owned(),
+    };
+    let timetable = input
+        .parse::<day13::Timetable>()
+        .unwrap_or_else(|e| panic!("{}", e));
+    let time =
+        option(args, "--time").map_or(timetable.earliest, |t| t.parse().expect("Invalid time"));
+
+    if let Some(n) = option(args, "--next") {
+        let n = n.parse().expect("Invalid number of departures");
+        for (time, bus) in timetable.next_departures(time, n) {
+            println!("{}\tbus {}", time, bus);
+        }
+    } else if args.iter().any(|a| a == "--at") {
+        for bus in timetable.departing_at(time) {
+            println!("bus {}", bus);
+        }
+    } else if let Some(pattern) = option(args, "--pattern") {
+        // Pairs of bus and offset, e.g. `7:0,13:1`.
+        let pattern = pattern
+            .split(',')
+            .map(|p| {
+                p.split_once(':')
+                    .and_then(|(bus, offset)| Some((bus.parse().ok()?, offset.parse().ok()?)))
+                    .unwrap_or_else(|| panic!("Invalid pattern entry {}, expected bus:offset", p))
+            })
+            .collect::<Vec<_>>();
+        match timetable.first_in_pattern(&pattern) {
+            Ok(res) => println!("first {}, every {}", res.residue, res.modulus),
+            Err(e) => println!("{}", e),
+        }
+    } else if let Some(window) = option(args, "--grid") {
+        let (from, to) = window
+            .split_once("..")
+            .and_then(|(from, to)| Some((from.parse().ok()?, to.parse().ok()?)))
+            .expect("Invalid window, expected from..to");
+        print!("{}", timetable.render(from, to));
+    } else {
+        if let Some((next, bus)) = timetable.next_departures(time, 1).first() {
+            println!("next\t{}\tbus {}", next, bus);
+        }
+        match timetable.first_in_pattern(&timetable.pattern()) {
+            Ok(res) => println!("pattern\tfirst {}, every {}", res.residue, res.modulus),
+            Err(e) => println!("pattern\t{}", e),
+        }
+    }
+}
+
+fn load_program(args: &[String]) -> day08::Program {
+    let input = match option(args, "--input") {
+        Some(file) => fs::read_to_string(file).expect("Could not read input"),
+        None => day08::Solver::load_input().into_owned(),
+    };
+    let isa = match option(args, "--isa") {
+        None | Some("handheld") => day08::InstructionSet::handheld(),
+        Some("extended") => day08::InstructionSet::extended(),
+        Some(isa) => panic!(
+            "Unknown instruction set {}, expected handheld or extended",
+            isa
+        ),
+    };
+    day08::Program::assemble(isa, &input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn run(args: &[String]) {
+    let tracing = option(args, "--trace");
+    if tracing.is_some() && !cfg!(feature = "trace") {
+        eprintln!("--trace has no effect, build with `--features trace` to enable it");
+    }
+
+    // cached answers would skip the traced code
+    let cache = if tracing.is_some() || args.iter().any(|a| a == "--no-cache") {
+        None
+    } else {
+        Cache::open()
+    };
+
+    for day in args.iter().filter_map(|s| s.parse::<u8>().ok()) {
+        if let Some(spec) = tracing {
+            trace::set_level(trace::level_for(day, spec).unwrap_or_else(|e| panic!("{}", e)));
+        }
+
         let (res1, res2) = match day {
-            1 => day01::Solver::solve(),
-            2 => day02::Solver::solve(),
-            3 => day03::Solver::solve(),
-            4 => day04::Solver::solve(),
-            5 => day05::Solver::solve(),
-            6 => day06::Solver::solve(),
-            7 => day07::Solver::solve(),
-            8 => day08::Solver::solve(),
-            9 => day09::Solver::solve(),
-            10 => day10::Solver::solve(),
-            11 => day11::Solver::solve(),
-            12 => day12::Solver::solve(),
-            13 => day13::Solver::solve(),
-            14 => day14::Solver::solve(),
-            15 => day15::Solver::solve(),
-            16 => day16::Solver::solve(),
-            17 => day17::Solver::solve(),
-            18 => day18::Solver::solve(),
-            19 => day19::Solver::solve(),
-            20 => day20::Solver::solve(),
-            21 => day21::Solver::solve(),
-            22 => day22::Solver::solve(),
-            23 => day23::Solver::solve(),
-            24 => day24::Solver::solve(),
-            25 => day25::Solver::solve(),
+            1 => solve::<day01::Solver>(day, cache.as_ref()),
+            2 => solve::<day02::Solver>(day, cache.as_ref()),
+            3 => solve::<day03::Solver>(day, cache.as_ref()),
+            4 => solve::<day04::Solver>(day, cache.as_ref()),
+            5 => solve::<day05::Solver>(day, cache.as_ref()),
+            6 => solve::<day06::Solver>(day, cache.as_ref()),
+            7 => solve::<day07::Solver>(day, cache.as_ref()),
+            8 => solve::<day08::Solver>(day, cache.as_ref()),
+            9 => solve::<day09::Solver>(day, cache.as_ref()),
+            10 => solve::<day10::Solver>(day, cache.as_ref()),
+            11 => solve::<day11::Solver>(day, cache.as_ref()),
+            12 => solve::<day12::Solver>(day, cache.as_ref()),
+            13 => solve::<day13::Solver>(day, cache.as_ref()),
+            14 => solve::<day14::Solver>(day, cache.as_ref()),
+            15 => solve::<day15::Solver>(day, cache.as_ref()),
+            16 => solve::<day16::Solver>(day, cache.as_ref()),
+            17 => solve::<day17::Solver>(day, cache.as_ref()),
+            18 => solve::<day18::Solver>(day, cache.as_ref()),
+            19 => solve::<day19::Solver>(day, cache.as_ref()),
+            20 => solve::<day20::Solver>(day, cache.as_ref()),
+            21 => solve::<day21::Solver>(day, cache.as_ref()),
+            22 => solve::<day22::Solver>(day, cache.as_ref()),
+            23 => solve::<day23::Solver>(day, cache.as_ref()),
+            24 => solve::<day24::Solver>(day, cache.as_ref()),
+            25 => solve::<day25::Solver>(day, cache.as_ref()),
             x => unimplemented!("Day {} is not yet implemented", x),
         };
 
@@ -68,3 +693,54 @@ fn main() {
         println!("Day {:02} Part 2:\t{}", day, res2);
     }
 }
+
+fn watch(args: &[String]) {
+    let day = args
+        .first()
+        .and_then(|s| s.parse::<u8>().ok())
+        .expect("Usage: watch <day> [--exe]");
+    let exe = args.iter().any(|a| a == "--exe");
+
+    match day {
+        1 => watch::watch::<day01::Solver>(day, exe),
+        2 => watch::watch::<day02::Solver>(day, exe),
+        3 => watch::watch::<day03::Solver>(day, exe),
+        4 => watch::watch::<day04::Solver>(day, exe),
+        5 => watch::watch::<day05::Solver>(day, exe),
+        6 => watch::watch::<day06::Solver>(day, exe),
+        7 => watch::watch::<day07::Solver>(day, exe),
+        8 => watch::watch::<day08::Solver>(day, exe),
+        9 => watch::watch::<day09::Solver>(day, exe),
+        10 => watch::watch::<day10::Solver>(day, exe),
+        11 => watch::watch::<day11::Solver>(day, exe),
+        12 => watch::watch::<day12::Solver>(day, exe),
+        13 => watch::watch::<day13::Solver>(day, exe),
+        14 => watch::watch::<day14::Solver>(day, exe),
+        15 => watch::watch::<day15::Solver>(day, exe),
+        16 => watch::watch::<day16::Solver>(day, exe),
+        17 => watch::watch::<day17::Solver>(day, exe),
+        18 => watch::watch::<day18::Solver>(day, exe),
+        19 => watch::watch::<day19::Solver>(day, exe),
+        20 => watch::watch::<day20::Solver>(day, exe),
+        21 => watch::watch::<day21::Solver>(day, exe),
+        22 => watch::watch::<day22::Solver>(day, exe),
+        23 => watch::watch::<day23::Solver>(day, exe),
+        24 => watch::watch::<day24::Solver>(day, exe),
+        25 => watch::watch::<day25::Solver>(day, exe),
+        x => {
+            eprintln!("Day {} is not yet implemented", x);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn solve<S>(day: u8, cache: Option<&Cache>) -> (Box<dyn Display>, Box<dyn Display>)
+where
+    S: Solution,
+    S::Output: Display + 'static,
+{
+    match cache {
+        Some(cache) => S::solve_cached(day, cache),
+        None => S::solve(),
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{As, Blocks, Parsing, PuzzleInput, Solution};
+use std::{
+    fmt::{Debug, Display},
+    io::{BufRead, BufReader, Read},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+pub trait StreamInput {
+    type Item;
+    type Iter<R: BufRead>: Iterator<Item = Self::Item>;
+
+    fn from_reader<R: BufRead>(reader: R) -> Self::Iter<R>;
+}
+
+pub struct Lines<R, T> {
+    reader: R,
+    line: String,
+    parse: fn(&str) -> T,
+}
+
+impl<R: BufRead, T> Lines<R, T> {
+    fn new(reader: R, parse: fn(&str) -> T) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            parse,
+        }
+    }
+}
+
+impl<R: BufRead, T> Iterator for Lines<R, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            let read = self.reader.read_line(&mut self.line).unwrap();
+            if read == 0 {
+                return None;
+            }
+            let line = self.line.trim();
+            if !line.is_empty() {
+                return Some((self.parse)(line));
+            }
+        }
+    }
+}
+
+impl<T> StreamInput for Parsing<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    type Item = T;
+    type Iter<R: BufRead> = Lines<R, T>;
+
+    fn from_reader<R: BufRead>(reader: R) -> Self::Iter<R> {
+        Lines::new(reader, |l| T::from_str(l).unwrap())
+    }
+}
+
+impl<T> StreamInput for As<T>
+where
+    T: From<String>,
+{
+    type Item = T;
+    type Iter<R: BufRead> = Lines<R, T>;
+
+    fn from_reader<R: BufRead>(reader: R) -> Self::Iter<R> {
+        Lines::new(reader, |l| T::from(String::from(l)))
+    }
+}
+
+pub struct Chunks<R, T> {
+    reader: R,
+    line: String,
+    block: String,
+    _item: PhantomData<T>,
+}
+
+impl<R: BufRead, T: PuzzleInput> Iterator for Chunks<R, T> {
+    type Item = T::Out;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.block.clear();
+        loop {
+            self.line.clear();
+            let read = self.reader.read_line(&mut self.line).unwrap();
+            if read == 0 || self.line.trim().is_empty() {
+                if read == 0 && self.block.is_empty() {
+                    return None;
+                }
+                if !self.block.is_empty() {
+                    return Some(T::from_input(self.block.trim_end()));
+                }
+            } else {
+                self.block.push_str(&self.line);
+            }
+        }
+    }
+}
+
+impl<T> StreamInput for Blocks<T>
+where
+    T: PuzzleInput,
+{
+    type Item = T::Out;
+    type Iter<R: BufRead> = Chunks<R, T>;
+
+    fn from_reader<R: BufRead>(reader: R) -> Self::Iter<R> {
+        Chunks {
+            reader,
+            line: String::new(),
+            block: String::new(),
+            _item: PhantomData,
+        }
+    }
+}
+
+pub trait StreamingSolution: Solution
+where
+    Self::Input: StreamInput,
+{
+    fn run_stream<I>(input: I) -> (Self::Output, Self::Output)
+    where
+        I: Iterator<Item = <Self::Input as StreamInput>::Item>;
+
+    #[inline]
+    fn run_reader<R: Read>(reader: R) -> (Self::Output, Self::Output) {
+        let input = <Self::Input as StreamInput>::from_reader(BufReader::new(reader));
+        Self::run_stream(input)
+    }
+
+    #[inline]
+    fn solve_reader<R: Read>(reader: R) -> (Box<dyn Display>, Box<dyn Display>)
+    where
+        Self::Output: Display + 'static,
+    {
+        let (res1, res2) = Self::run_reader(reader);
+        (Box::new(res1), Box::new(res2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_skip_blank_and_trim() {
+        let input = "  1\n\n2  \n   \n3";
+        let items = <Parsing<u32>>::from_reader(input.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn blocks_match_in_memory_split() {
+        let input = "a\nb\n\nc\n\n\nd\ne\n";
+        let streamed = <Blocks<As<String>>>::from_reader(input.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(
+            streamed,
+            vec![
+                vec![String::from("a"), String::from("b")],
+                vec![String::from("c")],
+                vec![String::from("d"), String::from("e")],
+            ]
+        );
+    }
+}
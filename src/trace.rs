@@ -0,0 +1,174 @@
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+    time::Instant,
+};
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Off,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Level::Off),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!(
+                "unknown level {}, expected off, info, debug or trace",
+                s
+            )),
+        }
+    }
+}
+
+// Parses `--trace` values like `debug` or `16=info,22=trace`, a plain
+// level applies to all days that are not listed explicitly.
+pub fn level_for(day: u8, spec: &str) -> Result<Level, String> {
+    let mut level = Level::Off;
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('=') {
+            Some((d, l)) => {
+                let d = d.parse::<u8>().map_err(|_| format!("invalid day {}", d))?;
+                let l = l.parse()?;
+                if d == day {
+                    return Ok(l);
+                }
+            }
+            None => level = part.parse()?,
+        }
+    }
+    Ok(level)
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn event(name: &str, fields: &[(&str, &dyn Debug)]) {
+    eprintln!(
+        "{:indent$}{}{}",
+        "",
+        name,
+        Fields(fields),
+        indent = indent()
+    );
+}
+
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Span {
+    pub fn disabled(name: &'static str) -> Self {
+        Self { name, start: None }
+    }
+
+    pub fn enter(name: &'static str, fields: &[(&str, &dyn Debug)]) -> Self {
+        eprintln!(
+            "{:indent$}-> {}{}",
+            "",
+            name,
+            Fields(fields),
+            indent = indent()
+        );
+        DEPTH.fetch_add(1, Ordering::Relaxed);
+        Self {
+            name,
+            start: Some(Instant::now()),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            DEPTH.fetch_sub(1, Ordering::Relaxed);
+            eprintln!(
+                "{:indent$}<- {} took={:?}",
+                "",
+                self.name,
+                start.elapsed(),
+                indent = indent()
+            );
+        }
+    }
+}
+
+fn indent() -> usize {
+    2 * DEPTH.load(Ordering::Relaxed)
+}
+
+struct Fields<'a>(&'a [(&'a str, &'a dyn Debug)]);
+
+impl std::fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0
+            .iter()
+            .try_for_each(|(key, value)| write!(f, " {}={:?}", key, value))
+    }
+}
+
+#[macro_export]
+macro_rules! event {
+    ($level:ident, $name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "trace")]
+        {
+            if $crate::trace::enabled($crate::trace::Level::$level) {
+                $crate::trace::event(
+                    $name,
+                    &[$((::std::stringify!($key), &$value as &dyn ::std::fmt::Debug)),*],
+                );
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! span {
+    ($level:ident, $name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "trace")]
+        let _span = if $crate::trace::enabled($crate::trace::Level::$level) {
+            $crate::trace::Span::enter(
+                $name,
+                &[$((::std::stringify!($key), &$value as &dyn ::std::fmt::Debug)),*],
+            )
+        } else {
+            $crate::trace::Span::disabled($name)
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_day_levels() {
+        assert_eq!(level_for(1, "debug"), Ok(Level::Debug));
+        assert_eq!(level_for(16, "16=info,22=trace"), Ok(Level::Info));
+        assert_eq!(level_for(22, "16=info,22=trace"), Ok(Level::Trace));
+        assert_eq!(level_for(1, "16=info,22=trace"), Ok(Level::Off));
+        assert_eq!(level_for(1, "info,22=trace"), Ok(Level::Info));
+        assert!(level_for(1, "loud").is_err());
+        assert!(level_for(1, "x=info").is_err());
+    }
+}
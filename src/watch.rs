@@ -0,0 +1,153 @@
+use crate::Solution;
+use std::{
+    env, fmt::Display, fs, panic, path::Path, process::Command, thread, time::Duration,
+    time::Instant, time::SystemTime,
+};
+
+const POLL: Duration = Duration::from_millis(500);
+
+struct Answers {
+    res1: String,
+    res2: String,
+    took: Duration,
+}
+
+// Re-runs the solver whenever its input file changes. With `exe` set, a
+// rebuilt executable replaces the running one, which then starts over.
+pub fn watch<S>(day: u8, exe: bool) -> !
+where
+    S: Solution,
+    S::Output: Display,
+{
+    let input = S::input_path();
+    let exe = if exe { env::current_exe().ok() } else { None };
+    let exe_modified = exe.as_deref().and_then(modified);
+
+    println!("Watching {}", input.display());
+    let mut last = None;
+    loop {
+        let input_modified = modified(&input);
+        let text = S::load_input();
+
+        let start = Instant::now();
+        let answers = panic::catch_unwind(|| S::run_on(&text));
+        let took = start.elapsed();
+        match answers {
+            Ok((res1, res2)) => {
+                let answers = Answers {
+                    res1: res1.to_string(),
+                    res2: res2.to_string(),
+                    took,
+                };
+                report(day, &answers, last.as_ref());
+                last = Some(answers);
+            }
+            Err(_) => println!("Day {:02} failed on the current input", day),
+        }
+
+        loop {
+            thread::sleep(POLL);
+            if let Some(exe) = exe.as_deref() {
+                if changed(exe, exe_modified) {
+                    // give the linker a moment to finish writing
+                    thread::sleep(POLL);
+                    restart(exe);
+                }
+            }
+            if changed(&input, input_modified) {
+                break;
+            }
+        }
+    }
+}
+
+fn report(day: u8, answers: &Answers, last: Option<&Answers>) {
+    let changed = |now: &str, before: Option<&str>| match before {
+        Some(before) if before != now => format!(" (was {})", before),
+        _ => String::new(),
+    };
+    let took = match last {
+        Some(last) => {
+            let diff = answers.took.as_secs_f64() - last.took.as_secs_f64();
+            format!("{:?} ({:+.3}ms)", answers.took, diff * 1000.0)
+        }
+        None => format!("{:?}", answers.took),
+    };
+
+    println!(
+        "Day {:02} Part 1:\t{}{}",
+        day,
+        answers.res1,
+        changed(&answers.res1, last.map(|l| l.res1.as_str()))
+    );
+    println!(
+        "Day {:02} Part 2:\t{}{}",
+        day,
+        answers.res2,
+        changed(&answers.res2, last.map(|l| l.res2.as_str()))
+    );
+    println!("Day {:02} Time:\t{}", day, took);
+}
+
+// Replaces this process on unix. Elsewhere the new process is started
+// and this one exits, so that restarts do not pile up.
+fn restart(exe: &Path) -> ! {
+    println!("Restarting {}", exe.display());
+    let mut command = Command::new(exe);
+    command.args(env::args_os().skip(1));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = command.exec();
+        panic!("Could not restart: {}", err)
+    }
+    #[cfg(not(unix))]
+    {
+        command.spawn().expect("Could not restart");
+        std::process::exit(0)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// A file that appears, disappears or gets a new timestamp has changed.
+fn changed(path: &Path, since: Option<SystemTime>) -> bool {
+    modified(path) != since
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn detects_changes() {
+        let dir = env::temp_dir().join(format!("aoc2020-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("input.txt");
+        let _ = fs::remove_file(&file);
+
+        assert_eq!(modified(&file), None);
+        assert!(!changed(&file, None));
+
+        fs::write(&file, "1\n").unwrap();
+        let first = modified(&file);
+        assert!(first.is_some());
+        assert!(changed(&file, None));
+        assert!(!changed(&file, first));
+
+        let later = first.unwrap() + Duration::from_secs(5);
+        File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|f| f.set_modified(later))
+            .unwrap();
+        assert!(changed(&file, first));
+        assert!(!changed(&file, Some(later)));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(changed(&file, Some(later)));
+    }
+}